@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ArchivedEvent::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(ArchivedEvent::Id))
+                    .col(uuid(ArchivedEvent::ProjectId))
+                    .col(string(ArchivedEvent::Event))
+                    .col(json_binary(ArchivedEvent::Request))
+                    .col(timestamp_with_time_zone(ArchivedEvent::ReceivedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_archived_event_received_at")
+                    .table(ArchivedEvent::Table)
+                    .col(ArchivedEvent::ReceivedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_archived_event_project_event")
+                    .table(ArchivedEvent::Table)
+                    .col(ArchivedEvent::ProjectId)
+                    .col(ArchivedEvent::Event)
+                    .col(ArchivedEvent::ReceivedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ArchivedEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ArchivedEvent {
+    Table,
+    Id,
+    ProjectId,
+    Event,
+    Request,
+    ReceivedAt,
+}
@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use migration::{Migrator, MigratorTrait};
+use notifico_core::error::EngineError;
+use notifico_core::http::admin::{ListQueryParams, ListableTrait, PaginatedResult};
+use notifico_core::pipeline::runner::ProcessEventRequest;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+
+/// Event as it was received by a worker.
+#[derive(Serialize, Debug)]
+pub struct ArchivedEvent {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub event: String,
+    pub request: ProcessEventRequest,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Selects archived events. Unset fields match every event.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct ArchiveFilter {
+    pub project_id: Option<Uuid>,
+    pub event: Option<String>,
+    /// Events received at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    /// Events received before this time.
+    pub to: Option<DateTime<Utc>>,
+    pub ids: Option<Vec<Uuid>>,
+}
+
+/// Keeps received events, so that they can be replayed later on,
+/// e.g. after a broken template has been fixed.
+pub struct DbEventArchive {
+    db: DatabaseConnection,
+}
+
+impl DbEventArchive {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    /// Stores the event. Events that are already archived are left as is.
+    pub async fn archive(&self, request: &ProcessEventRequest) -> Result<(), EngineError> {
+        entity::archived_event::Entity::insert(entity::archived_event::ActiveModel {
+            id: Set(request.id),
+            project_id: Set(request.project_id),
+            event: Set(request.event.clone()),
+            request: Set(serde_json::to_value(request).unwrap()),
+            received_at: Set(Utc::now().fixed_offset()),
+        })
+        .on_conflict(
+            OnConflict::column(entity::archived_event::Column::Id)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_archived_events(
+        &self,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<ArchivedEvent>, EngineError> {
+        let models = entity::archived_event::Entity::find()
+            .apply_params(&params)
+            .unwrap()
+            .all(&self.db)
+            .await?;
+
+        Ok(PaginatedResult {
+            items: models
+                .into_iter()
+                .map(ArchivedEvent::try_from)
+                .collect::<Result<_, _>>()?,
+            total_count: entity::archived_event::Entity::find()
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    /// Returns up to `limit` events matching the filter, ordered by ID.
+    /// Pass the ID of the last returned event as `after` to fetch the next page.
+    pub async fn find_events(
+        &self,
+        filter: &ArchiveFilter,
+        after: Option<Uuid>,
+        limit: u64,
+    ) -> Result<Vec<ArchivedEvent>, EngineError> {
+        let mut query = entity::archived_event::Entity::find();
+        if let Some(project_id) = filter.project_id {
+            query = query.filter(entity::archived_event::Column::ProjectId.eq(project_id));
+        }
+        if let Some(event) = &filter.event {
+            query = query.filter(entity::archived_event::Column::Event.eq(event));
+        }
+        if let Some(from) = filter.from {
+            query =
+                query.filter(entity::archived_event::Column::ReceivedAt.gte(from.fixed_offset()));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(entity::archived_event::Column::ReceivedAt.lt(to.fixed_offset()));
+        }
+        if let Some(ids) = &filter.ids {
+            query = query.filter(entity::archived_event::Column::Id.is_in(ids.clone()));
+        }
+        if let Some(after) = after {
+            query = query.filter(entity::archived_event::Column::Id.gt(after));
+        }
+
+        query
+            .order_by_asc(entity::archived_event::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(ArchivedEvent::try_from)
+            .collect()
+    }
+
+    /// Deletes events received before `before`. Returns the number of deleted events.
+    pub async fn cleanup(&self, before: DateTime<Utc>) -> Result<u64, EngineError> {
+        let result = entity::archived_event::Entity::delete_many()
+            .filter(entity::archived_event::Column::ReceivedAt.lt(before.fixed_offset()))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+impl TryFrom<entity::archived_event::Model> for ArchivedEvent {
+    type Error = EngineError;
+
+    fn try_from(value: entity::archived_event::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id,
+            project_id: value.project_id,
+            event: value.event,
+            request: Deserialize::deserialize(value.request)
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+            received_at: value.received_at.to_utc(),
+        })
+    }
+}
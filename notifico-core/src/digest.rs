@@ -0,0 +1,14 @@
+use crate::engine::EventContext;
+use crate::error::EngineError;
+use async_trait::async_trait;
+
+/// Collects events for the `core.digest` step until the digest is flushed.
+/// Must be shared by all workers.
+#[async_trait]
+pub trait DigestStore: Send + Sync {
+    /// Adds the event to the digest under `key`.
+    /// Returns `true` if there was no pending digest and the caller is responsible for flushing it.
+    async fn append(&self, key: &str, event: &EventContext) -> Result<bool, EngineError>;
+    /// Closes the digest and returns the collected events in the order they were added.
+    async fn take(&self, key: &str) -> Result<Vec<EventContext>, EngineError>;
+}
@@ -1,21 +1,249 @@
+use crate::digest::DigestStore;
 use crate::engine::{EnginePlugin, PipelineContext, StepOutput};
 use crate::error::EngineError;
-use crate::pipeline::runner::RecipientSelector;
-use crate::step::SerializedStep;
+use crate::recipient::Recipient;
+use crate::recorder::Recorder;
+use crate::step::{step_schemas, SerializedStep};
+use crate::throttle::ThrottleStore;
 use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use minijinja::Environment;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "step")]
 pub enum Step {
     #[serde(rename = "core.set_recipient")]
-    SetRecipient { recipient: RecipientSelector },
+    SetRecipient { recipient: Recipient },
+    /// Suspends the pipeline. Resumes at `until` (a path to a timestamp in the event context)
+    /// plus `duration` seconds. Any of them can be omitted.
+    #[serde(rename = "core.delay")]
+    Delay {
+        duration: Option<u64>,
+        until: Option<String>,
+    },
+    /// Evaluates `condition` and runs either `then` or `else` steps,
+    /// then continues with the rest of the pipeline.
+    #[serde(rename = "core.if")]
+    If {
+        condition: String,
+        #[serde(default)]
+        then: Vec<SerializedStep>,
+        #[serde(default, rename = "else")]
+        otherwise: Vec<SerializedStep>,
+    },
+    /// Interrupts the pipeline unless `condition` is true.
+    #[serde(rename = "core.filter")]
+    Filter { condition: String },
+    /// Reshapes the event context for the following steps, e.g. before templating.
+    /// Operations are applied in order, each one sees the result of the previous ones.
+    #[serde(rename = "core.transform")]
+    Transform { operations: Vec<TransformOperation> },
+    /// Tries channels in order, moving to the next one if the recipient has no contact
+    /// for the channel or any step of its branch fails.
+    #[serde(rename = "core.fallback")]
+    Fallback { channels: Vec<FallbackBranch> },
+    /// Interrupts the pipeline if the recipient has already been notified `limit` times
+    /// about this event through this channel within the last `window` seconds.
+    /// The window is limited to a year.
+    #[serde(rename = "core.throttle")]
+    Throttle {
+        limit: u32,
+        #[schemars(range(max = 31_622_400))]
+        window: u64,
+    },
+    /// Collects events for the recipient during `window` seconds into a single notification.
+    /// The first event continues the pipeline after the window, with all collected
+    /// event contexts in the `events` field. The rest of the events are interrupted.
+    /// Events are grouped by name, channel and the value of `key`, a path in the event context.
+    #[serde(rename = "core.digest")]
+    Digest { window: u64, key: Option<String> },
+    /// Defers the notification until the end of the recipient's quiet hours.
+    /// Notifications for which `bypass` evaluates to true are sent right away,
+    /// e.g. `bypass: "priority == 'urgent'"`.
+    #[serde(rename = "core.quiet_hours")]
+    QuietHours { bypass: Option<String> },
+    /// Flushes a digest. Inserted by `core.digest`, rejected in stored pipelines.
+    #[serde(rename = "core.digest_flush")]
+    DigestFlush { key: String },
 }
 
-pub const STEPS: &[&str] = &["core.set_recipient"];
+impl Step {
+    fn to_serialized(&self) -> SerializedStep {
+        let Value::Object(step) = serde_json::to_value(self).unwrap() else {
+            unreachable!("Steps are serialized as objects");
+        };
+        SerializedStep(step)
+    }
+}
+
+/// Operation of `core.transform`. Paths are dot-separated, e.g. `order.items.0.name`.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransformOperation {
+    Set {
+        path: String,
+        value: Value,
+    },
+    /// Does nothing if there is no value at `from`.
+    Copy {
+        from: String,
+        path: String,
+    },
+    Delete {
+        path: String,
+    },
+    /// Sets the value if it is missing or null.
+    Default {
+        path: String,
+        value: Value,
+    },
+    /// Stores the result of a minijinja expression, e.g. `items | map(attribute="price") | sum`.
+    /// The expression sees the same variables as `core.if` conditions.
+    Expression {
+        path: String,
+        expression: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct FallbackBranch {
+    pub channel: String,
+    pub steps: Vec<SerializedStep>,
+}
+
+/// Active `core.fallback` step. The current branch occupies pipeline steps up to `end`,
+/// `branches` are the channels left to try if it fails.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FallbackFrame {
+    pub end: usize,
+    pub branches: VecDeque<FallbackBranch>,
+    /// Channel the step has started in, restored once all of the branches have failed.
+    #[serde(default)]
+    pub channel: String,
+}
+
+pub const STEPS: &[&str] = &[
+    "core.set_recipient",
+    "core.delay",
+    "core.if",
+    "core.filter",
+    "core.transform",
+    "core.fallback",
+    "core.throttle",
+    "core.digest",
+    "core.quiet_hours",
+];
+
+/// Steps inserted by the plugin itself, see [`EnginePlugin::internal_steps`].
+const INTERNAL_STEPS: &[&str] = &["core.digest_flush"];
+
+/// Longest `core.throttle` window, a leap year. Also set in the schema of the step.
+const MAX_THROTTLE_WINDOW: u64 = 366 * 24 * 60 * 60;
+
+/// Field of the event context receiving the events collected by `core.digest`.
+const DIGEST_EVENTS: &str = "events";
+
+pub struct CorePlugin {
+    env: Environment<'static>,
+    throttle: Arc<dyn ThrottleStore>,
+    digests: Arc<dyn DigestStore>,
+    recorder: Arc<dyn Recorder>,
+}
+
+impl CorePlugin {
+    pub fn new(
+        throttle: Arc<dyn ThrottleStore>,
+        digests: Arc<dyn DigestStore>,
+        recorder: Arc<dyn Recorder>,
+    ) -> Self {
+        Self {
+            env: Environment::new(),
+            throttle,
+            digests,
+            recorder,
+        }
+    }
+
+    /// Evaluates a minijinja condition, e.g. `order.total > 100 and _.channel == "sms"`.
+    fn evaluate(&self, context: &PipelineContext, condition: &str) -> Result<bool, EngineError> {
+        Ok(self.eval_expression(context, condition)?.is_true())
+    }
+
+    /// Evaluates a minijinja expression.
+    ///
+    /// Event context fields are available at the top level, the rest of the pipeline context
+    /// is available under `_` (`event_name`, `channel`, `recipient`, `contact`, `plugin_contexts`).
+    fn eval_expression(
+        &self,
+        context: &PipelineContext,
+        expression: &str,
+    ) -> Result<minijinja::Value, EngineError> {
+        let mut expr_context = context.event_context.0.clone();
+
+        let mut pipeline_context = Map::new();
+        pipeline_context.insert(
+            "event_name".to_owned(),
+            Value::String(context.event_name.clone()),
+        );
+        pipeline_context.insert("channel".to_owned(), Value::String(context.channel.clone()));
+        pipeline_context.insert(
+            "recipient".to_owned(),
+            serde_json::to_value(&context.recipient).unwrap_or_default(),
+        );
+        pipeline_context.insert(
+            "contact".to_owned(),
+            serde_json::to_value(&context.contact).unwrap_or_default(),
+        );
+        pipeline_context.insert(
+            "plugin_contexts".to_owned(),
+            Value::Object(context.plugin_contexts.clone()),
+        );
+        expr_context.insert("_".to_owned(), Value::Object(pipeline_context));
+
+        let expr = self
+            .env
+            .compile_expression(expression)
+            .map_err(|e| EngineError::InvalidExpression(e.to_string()))?;
+        expr.eval(expr_context)
+            .map_err(|e| EngineError::InvalidExpression(e.to_string()))
+    }
 
-struct CorePlugin {}
+    fn transform(
+        &self,
+        context: &mut PipelineContext,
+        operation: TransformOperation,
+    ) -> Result<(), EngineError> {
+        let event_context = &mut context.event_context;
+        match operation {
+            TransformOperation::Set { path, value } => event_context.set_path(&path, value),
+            TransformOperation::Copy { from, path } => match event_context.get_path(&from) {
+                Some(value) => event_context.set_path(&path, value.clone()),
+                None => Ok(()),
+            },
+            TransformOperation::Delete { path } => {
+                event_context.remove_path(&path);
+                Ok(())
+            }
+            TransformOperation::Default { path, value } => match event_context.get_path(&path) {
+                Some(current) if !current.is_null() => Ok(()),
+                _ => event_context.set_path(&path, value),
+            },
+            TransformOperation::Expression { path, expression } => {
+                let result = self.eval_expression(context, &expression)?;
+                let value = serde_json::to_value(result)
+                    .map_err(|e| EngineError::InvalidExpression(e.to_string()))?;
+                context.event_context.set_path(&path, value)
+            }
+        }
+    }
+}
 
 #[async_trait]
 impl EnginePlugin for CorePlugin {
@@ -28,16 +256,336 @@ impl EnginePlugin for CorePlugin {
 
         match step {
             Step::SetRecipient { recipient } => {
-                let recipient = match recipient {
-                    RecipientSelector::Recipient(r) => r,
-                };
                 context.recipient = Some(recipient);
                 Ok(StepOutput::Continue)
             }
+            Step::Delay { duration, until } => {
+                let mut resume_at = match until {
+                    Some(path) => get_timestamp(context, &path)?,
+                    None => Utc::now(),
+                };
+                if let Some(duration) = duration {
+                    resume_at = i64::try_from(duration)
+                        .ok()
+                        .and_then(TimeDelta::try_seconds)
+                        .and_then(|delay| resume_at.checked_add_signed(delay))
+                        .ok_or_else(|| {
+                            EngineError::InvalidStep(serde::de::Error::custom(format!(
+                                "delay of {duration}s is out of range"
+                            )))
+                        })?;
+                }
+
+                if resume_at <= Utc::now() {
+                    return Ok(StepOutput::Continue);
+                }
+                Ok(StepOutput::Suspend { resume_at })
+            }
+            Step::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let branch = if self.evaluate(context, &condition)? {
+                    then
+                } else {
+                    otherwise
+                };
+                Ok(StepOutput::Branch(branch))
+            }
+            Step::Filter { condition } => {
+                if self.evaluate(context, &condition)? {
+                    Ok(StepOutput::Continue)
+                } else {
+                    Ok(StepOutput::Interrupt)
+                }
+            }
+            Step::Transform { operations } => {
+                // Operations see the results of the previous ones,
+                // but the context is only changed if all of them succeed
+                let original = context.event_context.clone();
+                for operation in operations {
+                    if let Err(err) = self.transform(context, operation) {
+                        context.event_context = original;
+                        return Err(err);
+                    }
+                }
+                Ok(StepOutput::Continue)
+            }
+            Step::Fallback { channels } => Ok(StepOutput::Fallback(channels)),
+            Step::Throttle { limit, window } => {
+                // Pipelines stored before the limit was introduced are not validated
+                if window > MAX_THROTTLE_WINDOW {
+                    return Err(EngineError::InvalidStep(serde::de::Error::custom(format!(
+                        "throttle window of {window}s is out of range"
+                    ))));
+                }
+                if context.dry_run {
+                    return Ok(StepOutput::Continue);
+                }
+
+                let window = Duration::from_secs(window);
+                let key = notification_key(context);
+                if self.throttle.try_acquire(&key, limit, window).await? {
+                    return Ok(StepOutput::Continue);
+                }
+
+                let reason = format!("throttled: more than {limit} in {}s", window.as_secs());
+                self.recorder
+                    .record_notification_suppressed(context, &reason)
+                    .await;
+                Ok(StepOutput::Interrupt)
+            }
+            Step::Digest { window, key } => {
+                let own_event = Value::Object(context.event_context.0.clone());
+                if context.dry_run {
+                    context
+                        .event_context
+                        .0
+                        .insert(DIGEST_EVENTS.to_owned(), Value::Array(vec![own_event]));
+                    return Ok(StepOutput::Continue);
+                }
+
+                let mut digest_key = notification_key(context);
+                if let Some(path) = key {
+                    let value = context
+                        .event_context
+                        .get_path(&path)
+                        .unwrap_or(&Value::Null);
+                    digest_key = format!("{digest_key}:{value}");
+                }
+
+                if !self
+                    .digests
+                    .append(&digest_key, &context.event_context)
+                    .await?
+                {
+                    // Added to a pending digest, which is flushed by its first event
+                    return Ok(StepOutput::Interrupt);
+                }
+
+                let flush = [
+                    Step::Delay {
+                        duration: Some(window),
+                        until: None,
+                    },
+                    Step::DigestFlush { key: digest_key },
+                ];
+                Ok(StepOutput::Branch(
+                    flush.iter().map(Step::to_serialized).collect(),
+                ))
+            }
+            Step::QuietHours { bypass } => {
+                let Some(recipient) = &context.recipient else {
+                    return Ok(StepOutput::Continue);
+                };
+                let Some(quiet_hours) = recipient.quiet_hours else {
+                    return Ok(StepOutput::Continue);
+                };
+                if let Some(bypass) = bypass {
+                    if self.evaluate(context, &bypass)? {
+                        return Ok(StepOutput::Continue);
+                    }
+                }
+
+                let now = Utc::now().with_timezone(&recipient.tz());
+                match quiet_hours.ends_at(now) {
+                    Some(resume_at) => Ok(StepOutput::Suspend { resume_at }),
+                    None => Ok(StepOutput::Continue),
+                }
+            }
+            Step::DigestFlush { key } => {
+                let events = self.digests.take(&key).await?;
+                if events.is_empty() {
+                    // The events have been flushed along with the previous digest
+                    return Ok(StepOutput::Interrupt);
+                }
+
+                let events = events
+                    .into_iter()
+                    .map(|event| Value::Object(event.0))
+                    .collect();
+                context
+                    .event_context
+                    .0
+                    .insert(DIGEST_EVENTS.to_owned(), Value::Array(events));
+                Ok(StepOutput::Continue)
+            }
         }
     }
 
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| s.into()).collect()
     }
+
+    fn internal_steps(&self) -> Vec<Cow<'static, str>> {
+        INTERNAL_STEPS.iter().map(|&s| s.into()).collect()
+    }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
+
+    fn nested_steps(&self, step: &SerializedStep) -> Vec<(String, SerializedStep)> {
+        let Ok(step) = step.clone().convert_step::<Step>() else {
+            return vec![];
+        };
+
+        let branch = |name: &str, steps: Vec<SerializedStep>| {
+            steps
+                .into_iter()
+                .enumerate()
+                .map(|(idx, step)| (format!("/{name}/{idx}"), step))
+                .collect::<Vec<_>>()
+        };
+        match step {
+            Step::If {
+                then, otherwise, ..
+            } => [branch("then", then), branch("else", otherwise)].concat(),
+            Step::Fallback { channels } => channels
+                .into_iter()
+                .enumerate()
+                .flat_map(|(idx, channel)| branch(&format!("channels/{idx}/steps"), channel.steps))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Identifies notifications of the same event to the same recipient through the same channel.
+/// Falls back to the contact if the recipient has no ID.
+fn notification_key(context: &PipelineContext) -> String {
+    let recipient_id = context.recipient.as_ref().map(|r| r.id);
+    let recipient = match (recipient_id, &context.contact) {
+        (Some(id), _) if !id.is_nil() => id.to_string(),
+        (_, Some(contact)) => contact.clone().into_json().to_string(),
+        (_, None) => String::new(),
+    };
+    format!(
+        "{}:{recipient}:{}:{}",
+        context.project_id, context.event_name, context.channel
+    )
+}
+
+/// Reads a timestamp from the event context. Accepts RFC 3339 strings and UNIX timestamps.
+fn get_timestamp(context: &PipelineContext, path: &str) -> Result<DateTime<Utc>, EngineError> {
+    let timestamp = match context.event_context.get_path(path) {
+        Some(Value::String(s)) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc)),
+        Some(Value::Number(n)) => n.as_i64().and_then(|n| DateTime::from_timestamp(n, 0)),
+        _ => None,
+    };
+    timestamp.ok_or_else(|| EngineError::InvalidContextValue(path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Engine, EventContext};
+    use crate::recorder::BaseRecorder;
+    use serde_json::json;
+
+    struct StubStore;
+
+    #[async_trait]
+    impl ThrottleStore for StubStore {
+        async fn try_acquire(
+            &self,
+            _key: &str,
+            _limit: u32,
+            _window: Duration,
+        ) -> Result<bool, EngineError> {
+            unimplemented!()
+        }
+    }
+
+    #[async_trait]
+    impl DigestStore for StubStore {
+        async fn append(&self, _key: &str, _event: &EventContext) -> Result<bool, EngineError> {
+            unimplemented!()
+        }
+        async fn take(&self, _key: &str) -> Result<Vec<EventContext>, EngineError> {
+            unimplemented!()
+        }
+    }
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.add_plugin(Arc::new(CorePlugin::new(
+            Arc::new(StubStore),
+            Arc::new(StubStore),
+            Arc::new(BaseRecorder::new()),
+        )));
+        engine
+    }
+
+    fn validate(steps: Value) -> Vec<String> {
+        let steps: Vec<SerializedStep> = serde_json::from_value(steps).unwrap();
+        engine()
+            .validate_steps(&steps)
+            .into_iter()
+            .map(|e| e.path)
+            .collect()
+    }
+
+    #[test]
+    fn throttle_window_is_limited() {
+        let step = |window: u64| json!([{"step": "core.throttle", "limit": 1, "window": window}]);
+        assert!(validate(step(MAX_THROTTLE_WINDOW)).is_empty());
+        assert_eq!(validate(step(MAX_THROTTLE_WINDOW + 1)), vec!["/0/window"]);
+    }
+
+    #[test]
+    fn unknown_key_is_rejected() {
+        let steps = json!([{"step": "core.filter", "condition": "true", "conditon": "false"}]);
+        let steps: Vec<SerializedStep> = serde_json::from_value(steps).unwrap();
+        let errors = engine().validate_steps(&steps);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/0");
+        assert!(errors[0].message.contains("conditon"));
+    }
+
+    #[test]
+    fn retry_is_accepted() {
+        let step =
+            |retry: Value| json!([{"step": "core.filter", "condition": "true", "retry": retry}]);
+        assert!(validate(step(json!({"max_attempts": 3, "multiplier": 1.5}))).is_empty());
+        assert_eq!(
+            validate(step(json!({"max_attempts": "3"}))),
+            vec!["/0/retry/max_attempts"]
+        );
+    }
+
+    #[test]
+    fn nested_errors_have_full_path() {
+        let steps = json!([
+            {"step": "core.filter", "condition": "true"},
+            {
+                "step": "core.if",
+                "condition": "true",
+                "then": [{"step": "core.throttle", "limit": "1", "window": 60}],
+                "else": [
+                    {"step": "core.filter", "condition": "true"},
+                    {"step": "core.unknown"}
+                ]
+            },
+            {
+                "step": "core.fallback",
+                "channels": [
+                    {"channel": "email", "steps": []},
+                    {"channel": "sms", "steps": [{"step": "core.delay", "duration": -1}]}
+                ]
+            }
+        ]);
+        assert_eq!(
+            validate(steps),
+            vec![
+                "/1/then/0/limit",
+                "/1/else/1/step",
+                "/2/channels/1/steps/0/duration"
+            ]
+        );
+    }
 }
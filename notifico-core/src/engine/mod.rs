@@ -1,26 +1,106 @@
 use crate::error::EngineError;
+use crate::metrics::{MESSAGES_FAILED, MESSAGES_SENT, STEPS_EXECUTED, STEP_DURATION};
 use crate::recipient::{Contact, Recipient, TypedContact};
-use crate::step::SerializedStep;
+use crate::recorder::Recorder;
+use crate::step::{SerializedStep, StepValidationError};
 use crate::templater::RenderedTemplate;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
-use tracing::instrument;
+use std::time::Instant;
+use tracing::{error, info, info_span, instrument, Span};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
 mod core;
 mod plugin;
 
+pub use self::core::{CorePlugin, FallbackBranch, FallbackFrame};
 pub use plugin::{EnginePlugin, StepOutput};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(transparent)]
 pub struct EventContext(pub Map<String, Value>);
 
+impl EventContext {
+    /// Looks up a value by a dot-separated path, e.g. `order.items.0.name`.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        let mut parts = path.split('.');
+        let mut value = self.0.get(parts.next()?)?;
+        for part in parts {
+            value = match value {
+                Value::Object(map) => map.get(part)?,
+                Value::Array(array) => array.get(part.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let mut parts = path.split('.');
+        let mut value = self.0.get_mut(parts.next()?)?;
+        for part in parts {
+            value = match value {
+                Value::Object(map) => map.get_mut(part)?,
+                Value::Array(array) => array.get_mut(part.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Sets a value by a dot-separated path, creating missing objects on the way.
+    /// Fails if the path is empty, goes through a scalar or past the end of an array.
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<(), EngineError> {
+        let invalid = || EngineError::InvalidContextValue(path.to_string());
+        if path.split('.').any(str::is_empty) {
+            return Err(invalid());
+        }
+
+        let mut parts = path.split('.');
+        let first = parts.next().ok_or_else(invalid)?;
+        let mut target = self.0.entry(first).or_insert(Value::Null);
+        for part in parts {
+            if target.is_null() {
+                *target = Value::Object(Map::new());
+            }
+            target = match target {
+                Value::Object(map) => map.entry(part).or_insert(Value::Null),
+                Value::Array(array) => part
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|idx| array.get_mut(idx))
+                    .ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+        }
+        *target = value;
+        Ok(())
+    }
+
+    /// Removes a value by a dot-separated path, returning it if it existed.
+    pub fn remove_path(&mut self, path: &str) -> Option<Value> {
+        let Some((parent, field)) = path.rsplit_once('.') else {
+            return self.0.remove(path);
+        };
+        match self.get_path_mut(parent)? {
+            Value::Object(map) => map.remove(field),
+            Value::Array(array) => {
+                let idx = field.parse::<usize>().ok()?;
+                (idx < array.len()).then(|| array.remove(idx))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Key in `plugin_contexts` holding IDs of messages sent by the current step.
+const SENT_MESSAGES: &str = "sent_messages";
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Message {
     pub id: Uuid,
@@ -30,10 +110,21 @@ pub struct Message {
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct PipelineContext {
     pub step_number: usize,
+    /// Number of retries of the current step, reset after the step succeeds.
+    #[serde(default)]
+    pub retry_attempt: u32,
+    /// Set for dry-runs: transports must not send anything.
+    #[serde(default)]
+    pub dry_run: bool,
 
     pub project_id: Uuid,
     pub event_id: Uuid,
     pub notification_id: Uuid,
+    /// Pipeline revision that produced the notification.
+    #[serde(default)]
+    pub pipeline_id: Uuid,
+    #[serde(default)]
+    pub pipeline_revision: i32,
 
     pub recipient: Option<Recipient>,
     pub contact: Option<Contact>,
@@ -42,6 +133,13 @@ pub struct PipelineContext {
     pub plugin_contexts: Map<String, Value>,
     pub messages: Vec<Message>,
     pub channel: String,
+    /// Stack of active `core.fallback` steps, innermost last.
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackFrame>,
+    /// Name of the credential used by the step being executed, if any.
+    /// Set by the engine, so that recorders can label metrics with it.
+    #[serde(skip)]
+    pub credential: Option<String>,
 }
 
 impl PipelineContext {
@@ -56,6 +154,130 @@ impl PipelineContext {
 
         contact.clone().into_contact()
     }
+
+    /// Records the outcome of sending a message, called by transports for every message.
+    ///
+    /// Transient errors are returned, so that the step is retried. Permanent ones are returned
+    /// inside a `core.fallback` channel branch, so that the next channel is tried; elsewhere
+    /// the message is skipped and the transport goes on with the remaining messages.
+    pub async fn record_delivery(
+        &mut self,
+        recorder: &dyn Recorder,
+        message_id: Uuid,
+        result: Result<(), EngineError>,
+    ) -> Result<(), EngineError> {
+        let credential = self.credential.clone().unwrap_or_default();
+        let error = match result {
+            Ok(()) => {
+                metrics::counter!(
+                    MESSAGES_SENT,
+                    "channel" => self.channel.clone(),
+                    "credential" => credential,
+                )
+                .increment(1);
+                info!(
+                    "Message sent: {}/{}/{message_id}",
+                    self.event_id, self.notification_id
+                );
+                recorder.record_message_sent(self, message_id).await;
+                self.mark_message_sent(message_id);
+                return Ok(());
+            }
+            Err(error) => error,
+        };
+
+        let message = match &error {
+            EngineError::TransientError(message) | EngineError::DeliveryFailed(message) => {
+                message.clone()
+            }
+            error => format!("{error:?}"),
+        };
+        metrics::counter!(
+            MESSAGES_FAILED,
+            "channel" => self.channel.clone(),
+            "credential" => credential,
+        )
+        .increment(1);
+        error!(
+            "Failed to send message: {}/{}/{message_id} - {message}",
+            self.event_id, self.notification_id
+        );
+        recorder
+            .record_message_failed(self, message_id, &message)
+            .await;
+        if error.is_transient() || self.in_fallback() {
+            return Err(error);
+        }
+        self.mark_message_failed(message_id);
+        Ok(())
+    }
+
+    /// Whether the current step runs in a `core.fallback` channel branch.
+    pub fn in_fallback(&self) -> bool {
+        !self.fallbacks.is_empty()
+    }
+
+    /// Messages of the current step that have not been sent yet.
+    /// Transports iterate over these, so that a retried step does not re-send delivered messages.
+    pub fn pending_messages(&self) -> Vec<Message> {
+        let sent = self
+            .plugin_contexts
+            .get(SENT_MESSAGES)
+            .and_then(Value::as_array);
+        self.messages
+            .iter()
+            .filter(|message| {
+                !sent.is_some_and(|sent| sent.contains(&Value::String(message.id.to_string())))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Remembers that the message has been delivered by the current step.
+    fn mark_message_sent(&mut self, message_id: Uuid) {
+        self.mark_message_done(message_id);
+    }
+
+    /// Remembers that the message has failed permanently, so that a retry of the step
+    /// does not try it again.
+    fn mark_message_failed(&mut self, message_id: Uuid) {
+        self.mark_message_done(message_id);
+    }
+
+    fn mark_message_done(&mut self, message_id: Uuid) {
+        let sent = self
+            .plugin_contexts
+            .entry(SENT_MESSAGES)
+            .or_insert_with(|| Value::Array(vec![]));
+        if let Value::Array(sent) = sent {
+            sent.push(Value::String(message_id.to_string()));
+        }
+    }
+
+    /// Moves on to the next step.
+    pub(crate) fn finish_step(&mut self) {
+        self.step_number += 1;
+        self.retry_attempt = 0;
+        self.clear_sent_messages();
+    }
+
+    /// Forgets the messages delivered by the current step.
+    pub(crate) fn clear_sent_messages(&mut self) {
+        self.plugin_contexts.remove(SENT_MESSAGES);
+    }
+
+    /// Span of a call to an external delivery service, such as an SMTP server.
+    pub fn transport_span(&self, transport: &'static str, message_id: Uuid) -> Span {
+        info_span!(
+            "transport.send",
+            transport,
+            %message_id,
+            event_id = %self.event_id,
+            notification_id = %self.notification_id,
+            project = %self.project_id,
+            channel = %self.channel,
+        )
+    }
 }
 
 /// Engine is used to run steps in the pipeline.
@@ -63,6 +285,9 @@ impl PipelineContext {
 #[derive(Clone)]
 pub struct Engine {
     steps: HashMap<Cow<'static, str>, Arc<dyn EnginePlugin>>,
+    internal_steps: HashMap<Cow<'static, str>, Arc<dyn EnginePlugin>>,
+    /// Compiled step schemas, keyed by step name.
+    validators: HashMap<Cow<'static, str>, Arc<jsonschema::Validator>>,
 }
 
 impl Debug for Engine {
@@ -82,19 +307,45 @@ impl Engine {
     pub fn new() -> Self {
         Self {
             steps: Default::default(),
+            internal_steps: Default::default(),
+            validators: Default::default(),
         }
     }
 
+    /// Registers the steps of the plugin.
+    ///
+    /// # Panics
+    /// If any of the step schemas of the plugin is not a valid JSON Schema.
     pub fn add_plugin(&mut self, plugin: Arc<dyn EnginePlugin + 'static>) {
-        self.steps.extend(
+        let steps = plugin.steps();
+        for (name, schema) in plugin.step_schemas() {
+            if steps.contains(&name) {
+                let validator = jsonschema::validator_for(&schema).expect("Invalid step schema");
+                self.validators.insert(name, Arc::new(validator));
+            }
+        }
+
+        self.steps
+            .extend(steps.into_iter().map(|step| (step, plugin.clone())));
+        self.internal_steps.extend(
             plugin
-                .steps()
+                .internal_steps()
                 .into_iter()
                 .map(|step| (step, plugin.clone())),
         );
     }
 
-    #[instrument]
+    #[instrument(
+        name = "execute_step",
+        skip_all,
+        fields(
+            step = step.get_type(),
+            event_id = %context.event_id,
+            notification_id = %context.notification_id,
+            project = %context.project_id,
+            channel = %context.channel,
+        )
+    )]
     pub async fn execute_step(
         &self,
         context: &mut PipelineContext,
@@ -102,10 +353,248 @@ impl Engine {
     ) -> Result<StepOutput, EngineError> {
         let step_type = step.get_type();
 
-        let Some(plugin) = self.steps.get(step_type) else {
+        let plugin = self
+            .steps
+            .get(step_type)
+            .or_else(|| self.internal_steps.get(step_type));
+        let Some(plugin) = plugin else {
             return Err(EngineError::PluginNotFound(step_type.into()));
         };
 
-        plugin.execute_step(context, step).await
+        context.credential = step
+            .0
+            .get("credential")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+
+        let started = Instant::now();
+        let result = plugin.execute_step(context, step).await;
+
+        let step_label = step_type.to_owned();
+        let result_label = if result.is_ok() { "ok" } else { "error" };
+        metrics::counter!(STEPS_EXECUTED, "step" => step_label.clone(), "result" => result_label)
+            .increment(1);
+        metrics::histogram!(STEP_DURATION, "step" => step_label).record(started.elapsed());
+        result
+    }
+
+    /// JSON Schemas of all registered steps, keyed by step name.
+    pub fn step_schemas(&self) -> BTreeMap<Cow<'static, str>, Value> {
+        self.steps
+            .iter()
+            .filter_map(|(name, plugin)| {
+                plugin
+                    .step_schemas()
+                    .into_iter()
+                    .find(|(schema_name, _)| schema_name == name)
+            })
+            .collect()
+    }
+
+    /// Validates steps against the schemas of their plugins, including nested steps.
+    /// Returns every problem found, with JSON Pointers relative to `steps`.
+    pub fn validate_steps(&self, steps: &[SerializedStep]) -> Vec<StepValidationError> {
+        let mut errors = vec![];
+        for (idx, step) in steps.iter().enumerate() {
+            self.validate_step(step, &format!("/{idx}"), &mut errors);
+        }
+        errors
+    }
+
+    fn validate_step(
+        &self,
+        step: &SerializedStep,
+        path: &str,
+        errors: &mut Vec<StepValidationError>,
+    ) {
+        let mut error =
+            |path: String, message: String| errors.push(StepValidationError { path, message });
+
+        let Some(step_type) = step.0.get("step").and_then(Value::as_str) else {
+            error(format!("{path}/step"), "Step type must be a string".into());
+            return;
+        };
+        let Some(plugin) = self.steps.get(step_type) else {
+            error(format!("{path}/step"), format!("Unknown step: {step_type}"));
+            return;
+        };
+
+        if let Some(validator) = self.validators.get(step_type) {
+            let instance = Value::Object(step.0.clone());
+            let mut valid = true;
+            for e in validator.iter_errors(&instance) {
+                valid = false;
+                error(format!("{path}{}", e.instance_path), e.to_string());
+            }
+            if !valid {
+                return;
+            }
+        }
+
+        // Covers plugins without schemas and values out of range of the policy fields
+        if let Err(EngineError::InvalidStep(e)) = step.retry_policy() {
+            error(format!("{path}/retry"), e.to_string());
+        }
+
+        for (nested_path, nested_step) in plugin.nested_steps(step) {
+            self.validate_step(&nested_step, &format!("{path}{nested_path}"), errors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Sends messages, failing the second one on the first attempt.
+    #[derive(Default)]
+    struct FlakyTransport {
+        sent: Mutex<Vec<Uuid>>,
+        attempts: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl EnginePlugin for FlakyTransport {
+        async fn execute_step(
+            &self,
+            context: &mut PipelineContext,
+            _step: &SerializedStep,
+        ) -> Result<StepOutput, EngineError> {
+            let attempt = {
+                let mut attempts = self.attempts.lock().unwrap();
+                *attempts += 1;
+                *attempts
+            };
+            for message in context.pending_messages() {
+                if message.id == context.messages[1].id && attempt == 1 {
+                    return Err(EngineError::TransientError("connection reset".into()));
+                }
+                self.sent.lock().unwrap().push(message.id);
+                context.mark_message_sent(message.id);
+            }
+            Ok(StepOutput::Continue)
+        }
+
+        fn steps(&self) -> Vec<Cow<'static, str>> {
+            vec!["test.send".into()]
+        }
+    }
+
+    fn event_context() -> EventContext {
+        serde_json::from_value(serde_json::json!({
+            "order": {"id": 7, "items": [{"name": "tea"}, {"name": "milk"}]}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn set_path_creates_objects() {
+        let mut context = event_context();
+        context.set_path("user.name", "Alice".into()).unwrap();
+        context.set_path("order.id", 8.into()).unwrap();
+
+        assert_eq!(context.get_path("user.name"), Some(&"Alice".into()));
+        assert_eq!(context.get_path("order.id"), Some(&8.into()));
+    }
+
+    #[test]
+    fn set_path_array_index() {
+        let mut context = event_context();
+        context
+            .set_path("order.items.1.name", "cream".into())
+            .unwrap();
+        assert_eq!(
+            context.get_path("order.items.1.name"),
+            Some(&"cream".into())
+        );
+
+        assert!(context
+            .set_path("order.items.2.name", "sugar".into())
+            .is_err());
+        assert!(context
+            .set_path("order.items.first", "sugar".into())
+            .is_err());
+    }
+
+    #[test]
+    fn set_path_through_scalar() {
+        let mut context = event_context();
+        assert!(context.set_path("order.id.value", 1.into()).is_err());
+        assert_eq!(context.get_path("order.id"), Some(&7.into()));
+    }
+
+    #[test]
+    fn set_path_empty() {
+        let mut context = event_context();
+        assert!(context.set_path("", 1.into()).is_err());
+        assert!(context.set_path("order..id", 1.into()).is_err());
+        assert_eq!(context.0, event_context().0);
+    }
+
+    #[test]
+    fn remove_path_array_index() {
+        let mut context = event_context();
+        assert_eq!(
+            context.remove_path("order.items.0"),
+            Some(serde_json::json!({"name": "tea"}))
+        );
+        assert_eq!(context.get_path("order.items.0.name"), Some(&"milk".into()));
+        assert_eq!(context.remove_path("order.items.1"), None);
+    }
+
+    #[test]
+    fn remove_path_through_scalar() {
+        let mut context = event_context();
+        assert_eq!(context.remove_path("order.id.value"), None);
+        assert_eq!(context.remove_path("order.missing.value"), None);
+        assert_eq!(context.0, event_context().0);
+    }
+
+    #[test]
+    fn remove_path_empty() {
+        let mut context = event_context();
+        assert_eq!(context.remove_path(""), None);
+        assert_eq!(context.0, event_context().0);
+    }
+
+    #[tokio::test]
+    async fn retried_step_skips_sent_messages() {
+        let transport = Arc::new(FlakyTransport::default());
+        let mut engine = Engine::new();
+        engine.add_plugin(transport.clone());
+
+        let step: SerializedStep = serde_json::from_value(serde_json::json!({
+            "step": "test.send"
+        }))
+        .unwrap();
+        let mut context = PipelineContext {
+            messages: vec![
+                Message {
+                    id: Uuid::now_v7(),
+                    content: Default::default(),
+                },
+                Message {
+                    id: Uuid::now_v7(),
+                    content: Default::default(),
+                },
+            ],
+            ..Default::default()
+        };
+        let ids: Vec<Uuid> = context.messages.iter().map(|m| m.id).collect();
+
+        let result = engine.execute_step(&mut context, &step).await;
+        assert!(matches!(result, Err(EngineError::TransientError(_))));
+
+        // The context is persisted with the suspended task in between.
+        let mut context: PipelineContext =
+            serde_json::from_value(serde_json::to_value(&context).unwrap()).unwrap();
+        let result = engine.execute_step(&mut context, &step).await;
+        assert!(matches!(result, Ok(StepOutput::Continue)));
+        assert_eq!(*transport.sent.lock().unwrap(), ids);
+
+        context.finish_step();
+        assert_eq!(context.pending_messages().len(), 2);
     }
 }
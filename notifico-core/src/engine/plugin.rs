@@ -1,13 +1,23 @@
-use crate::engine::PipelineContext;
+use crate::engine::{FallbackBranch, PipelineContext};
 use crate::error::EngineError;
 use crate::step::SerializedStep;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
 use std::any::Any;
 use std::borrow::Cow;
 
 pub enum StepOutput {
     Continue,
     Interrupt,
+    /// Suspend the pipeline and resume it from the next step not earlier than `resume_at`.
+    Suspend {
+        resume_at: DateTime<Utc>,
+    },
+    /// Run the given steps next, then continue with the rest of the pipeline.
+    Branch(Vec<SerializedStep>),
+    /// Run the first of the given channel branches that succeeds.
+    Fallback(Vec<FallbackBranch>),
 }
 
 #[async_trait]
@@ -21,4 +31,21 @@ pub trait EnginePlugin: Send + Sync + Any {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         vec![]
     }
+
+    /// Steps the plugin inserts into pipelines by itself, e.g. through [`StepOutput::Branch`].
+    /// They are executed like the others, but cannot be used in stored pipelines.
+    fn internal_steps(&self) -> Vec<Cow<'static, str>> {
+        vec![]
+    }
+
+    /// JSON Schemas of the steps, see [`crate::step::step_schemas`].
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        vec![]
+    }
+
+    /// Steps nested into `step`, such as `core.if` branches,
+    /// with their JSON Pointers relative to `step`.
+    fn nested_steps(&self, _step: &SerializedStep) -> Vec<(String, SerializedStep)> {
+        vec![]
+    }
 }
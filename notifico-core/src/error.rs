@@ -12,9 +12,23 @@ pub enum EngineError {
     InvalidContactFormat(serde_json::Error),
     TemplateRenderingError,
     MissingTemplateParameter(String),
-    InvalidRenderedTemplateFormat(Box<dyn Error>),
-    InternalError(Box<dyn Error>),
+    InvalidRenderedTemplateFormat(Box<dyn Error + Send + Sync>),
+    InternalError(Box<dyn Error + Send + Sync>),
     InvalidStep(serde_json::Error),
+    InvalidContextValue(String),
+    InvalidExpression(String),
+    /// Temporary failure (network error, rate limit, etc.), the step can be retried later.
+    TransientError(String),
+    /// Permanent failure of message delivery.
+    DeliveryFailed(String),
+    /// The change conflicts with a concurrent one and can be retried.
+    Conflict(String),
+}
+
+impl EngineError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, EngineError::TransientError(_))
+    }
 }
 
 impl From<DbErr> for EngineError {
@@ -1,11 +1,14 @@
 pub mod config;
 pub mod credentials;
 pub mod db;
+pub mod digest;
 pub mod engine;
 pub mod error;
 pub mod http;
+pub mod metrics;
 pub mod pipeline;
 pub mod recipient;
 pub mod recorder;
 pub mod step;
 pub mod templater;
+pub mod throttle;
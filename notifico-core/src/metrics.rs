@@ -0,0 +1,51 @@
+//! Names of the metrics recorded with the [`metrics`] facade.
+//!
+//! Nothing is collected unless the binary installs a metrics recorder, see `notifico-telemetry`.
+
+use metrics::{describe_counter, describe_histogram, Unit};
+
+pub const EVENTS_INGESTED: &str = "notifico_events_ingested_total";
+pub const EVENTS_DUPLICATE: &str = "notifico_events_duplicate_total";
+pub const PIPELINES_MATCHED: &str = "notifico_pipelines_matched_total";
+pub const STEPS_EXECUTED: &str = "notifico_steps_executed_total";
+pub const STEP_DURATION: &str = "notifico_step_duration_seconds";
+pub const MESSAGES_SENT: &str = "notifico_messages_sent_total";
+pub const MESSAGES_FAILED: &str = "notifico_messages_failed_total";
+pub const TEMPLATE_RENDER_DURATION: &str = "notifico_template_render_duration_seconds";
+pub const AMQP_RECONNECTS: &str = "notifico_amqp_reconnects_total";
+
+/// Registers descriptions of all metrics, shown as `# HELP` by Prometheus.
+pub fn describe() {
+    describe_counter!(EVENTS_INGESTED, "Events accepted by the ingest API");
+    describe_counter!(
+        EVENTS_DUPLICATE,
+        "Events skipped by workers as duplicates of already processed ones"
+    );
+    describe_counter!(
+        PIPELINES_MATCHED,
+        "Pipelines matched by processed events, counted once per recipient"
+    );
+    describe_counter!(
+        STEPS_EXECUTED,
+        "Pipeline steps executed, by step type and result"
+    );
+    describe_histogram!(
+        STEP_DURATION,
+        Unit::Seconds,
+        "Pipeline step execution time, by step type. Includes the network call for transport steps"
+    );
+    describe_counter!(MESSAGES_SENT, "Messages sent, by channel and credential");
+    describe_counter!(
+        MESSAGES_FAILED,
+        "Messages failed to be sent, by channel and credential"
+    );
+    describe_histogram!(
+        TEMPLATE_RENDER_DURATION,
+        Unit::Seconds,
+        "Template rendering time"
+    );
+    describe_counter!(
+        AMQP_RECONNECTS,
+        "Attempts to reconnect to the AMQP broker after losing the connection"
+    );
+}
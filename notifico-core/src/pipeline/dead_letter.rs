@@ -0,0 +1,93 @@
+use crate::engine::PipelineContext;
+use crate::error::EngineError;
+use crate::pipeline::runner::ProcessEventRequest;
+use crate::pipeline::Pipeline;
+use crate::step::SerializedStep;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Event or pipeline that has failed permanently, kept for inspection and replay.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    /// Nil for messages that could not be deserialized.
+    pub project_id: Uuid,
+    pub event_id: Option<Uuid>,
+    pub error: String,
+    /// Step that has failed, for pipelines.
+    pub step: Option<SerializedStep>,
+    pub payload: DeadLetterPayload,
+    pub created_at: DateTime<Utc>,
+    /// Last time the dead letter was replayed, if ever.
+    pub replayed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeadLetterPayload {
+    /// Message received by a worker that could not be deserialized.
+    Message { body: String },
+    /// Event that has failed before all of its pipelines were started.
+    /// `request.recipient` selects only the recipients whose pipelines were not started.
+    Event { request: ProcessEventRequest },
+    /// Pipeline that has failed at `context.step_number`.
+    Pipeline {
+        pipeline: Pipeline,
+        context: Box<PipelineContext>,
+    },
+    /// Scheduled pipeline that could not be deserialized, kept as stored.
+    Task {
+        pipeline: serde_json::Value,
+        context: serde_json::Value,
+    },
+}
+
+impl DeadLetterPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DeadLetterPayload::Message { .. } => "message",
+            DeadLetterPayload::Event { .. } => "event",
+            DeadLetterPayload::Pipeline { .. } => "pipeline",
+            DeadLetterPayload::Task { .. } => "task",
+        }
+    }
+}
+
+impl DeadLetter {
+    pub fn new(
+        project_id: Uuid,
+        event_id: Option<Uuid>,
+        error: String,
+        payload: DeadLetterPayload,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            project_id,
+            event_id,
+            error,
+            step: None,
+            payload,
+            created_at: Utc::now(),
+            replayed_at: None,
+        }
+    }
+}
+
+/// How a dead letter is replayed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayMode {
+    /// Pipelines are resumed from the failed step with the stored context.
+    #[default]
+    Stored,
+    /// Pipelines are restarted from the beginning using their current revision.
+    Current,
+}
+
+/// Durable storage for failed events and pipelines.
+#[async_trait]
+pub trait DeadLetterQueue: Send + Sync {
+    async fn push(&self, letter: DeadLetter) -> Result<(), EngineError>;
+}
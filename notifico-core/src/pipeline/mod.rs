@@ -1,8 +1,15 @@
+pub mod dead_letter;
+pub mod pattern;
+pub mod retry;
+pub mod revision;
 pub mod runner;
+pub mod scheduler;
+pub mod schema;
 pub mod storage;
 
 use crate::step::SerializedStep;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
@@ -10,12 +17,32 @@ pub struct Event {
     pub id: Uuid,
     pub project_id: Uuid,
     pub name: String,
+    /// JSON Schema of the event context, checked by the ingest. See [`schema::EventSchema`].
+    #[serde(default)]
+    pub schema: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Pipeline {
     pub id: Uuid,
     pub project_id: Uuid,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Disabled pipelines are not run for new events.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Pipelines with higher priority are started first.
+    #[serde(default)]
+    pub priority: i32,
+    /// Incremented on every change, see [`revision::PipelineRevision`].
+    #[serde(default)]
+    pub revision: i32,
     pub channel: String,
     pub steps: Vec<SerializedStep>,
 }
+
+fn default_enabled() -> bool {
+    true
+}
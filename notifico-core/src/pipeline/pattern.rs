@@ -0,0 +1,96 @@
+//! Glob-style event patterns, e.g. `billing.*`.
+//!
+//! `*` matches any sequence of characters, including dots and an empty one.
+//! `?` matches exactly one character. Everything else matches itself.
+
+/// Checks whether the event name matches the pattern.
+pub fn matches(pattern: &str, event_name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = event_name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and of the name character it currently extends to
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => {
+                // Let the last `*` swallow one more character and try again
+                let Some((star_p, star_n)) = star else {
+                    return false;
+                };
+                star = Some((star_p, star_n + 1));
+                p = star_p + 1;
+                n = star_n + 1;
+            }
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal() {
+        assert!(matches("billing.paid", "billing.paid"));
+        assert!(!matches("billing.paid", "billing.pai"));
+        assert!(!matches("billing.paid", "billing.paid2"));
+        assert!(!matches("", "billing"));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn star_at_start() {
+        assert!(matches("*.paid", "billing.paid"));
+        assert!(matches("*.paid", ".paid"));
+        assert!(matches("*paid", "billing.invoice.paid"));
+        assert!(!matches("*.paid", "billing.paid.late"));
+    }
+
+    #[test]
+    fn star_in_middle() {
+        assert!(matches("billing.*.paid", "billing.invoice.paid"));
+        assert!(matches("billing.*.paid", "billing..paid"));
+        assert!(matches("billing.*.paid", "billing.a.b.paid"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("billing.*.paid", "billing.paid"));
+        assert!(!matches("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn star_at_end() {
+        assert!(matches("billing.*", "billing.paid"));
+        assert!(matches("billing.*", "billing."));
+        assert!(matches("billing*", "billing"));
+        assert!(matches("*", ""));
+        assert!(matches("**", "anything"));
+        assert!(!matches("billing.*", "shipping.sent"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(matches("order.?", "order.1"));
+        assert!(matches("??", "ab"));
+        assert!(!matches("order.?", "order."));
+        assert!(!matches("order.?", "order.12"));
+        assert!(matches("order.?*", "order.12"));
+    }
+
+    #[test]
+    fn multi_byte_characters() {
+        assert!(matches("заказ.?", "заказ.й"));
+        assert!(matches("?.paid", "€.paid"));
+        assert!(matches("*🎉", "party🎉"));
+        assert!(!matches("заказ.?", "заказ.йй"));
+        assert!(!matches("заказ.*", "заказы.оплачен"));
+    }
+}
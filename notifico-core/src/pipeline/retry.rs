@@ -0,0 +1,63 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Controls how a step failed with a transient error is retried.
+///
+/// Can be set for a particular step with the `retry` key:
+/// `{"step": "smtp.send", "credential": "main", "retry": {"max_attempts": 3}}`
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct RetryPolicy {
+    /// Maximum number of retries. Zero disables retrying.
+    #[serde(default = "RetryPolicy::default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, in seconds.
+    #[serde(default = "RetryPolicy::default_initial_interval")]
+    pub initial_interval: u64,
+    /// Factor by which the delay grows after every attempt.
+    #[serde(default = "RetryPolicy::default_multiplier")]
+    pub multiplier: f64,
+    /// Upper bound for the delay, in seconds.
+    #[serde(default = "RetryPolicy::default_max_interval")]
+    pub max_interval: u64,
+}
+
+impl RetryPolicy {
+    fn default_max_attempts() -> u32 {
+        5
+    }
+
+    fn default_initial_interval() -> u64 {
+        10
+    }
+
+    fn default_multiplier() -> f64 {
+        2.0
+    }
+
+    fn default_max_interval() -> u64 {
+        60 * 60
+    }
+
+    /// Returns the delay before the given retry attempt (starting from 1),
+    /// or `None` if attempts are exhausted.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+
+        let delay = self.initial_interval as f64 * self.multiplier.powi(attempt as i32 - 1);
+        Some(Duration::from_secs_f64(delay.min(self.max_interval as f64)))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::default_max_attempts(),
+            initial_interval: Self::default_initial_interval(),
+            multiplier: Self::default_multiplier(),
+            max_interval: Self::default_max_interval(),
+        }
+    }
+}
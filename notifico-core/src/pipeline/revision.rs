@@ -0,0 +1,80 @@
+use crate::pipeline::Pipeline;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Immutable snapshot of a pipeline, created on every change.
+/// Event assignments are not part of the snapshot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipelineRevision {
+    pub pipeline_id: Uuid,
+    pub revision: i32,
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub pipeline: Pipeline,
+}
+
+/// Single difference between two revisions.
+/// `path` is a JSON Pointer into the pipeline, a missing value means the field was added or removed.
+#[derive(Clone, Debug, Serialize)]
+pub struct RevisionChange {
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl PipelineRevision {
+    /// Lists the changes made to the pipeline between `self` and `other`.
+    pub fn diff(&self, other: &PipelineRevision) -> Vec<RevisionChange> {
+        let mut old = serde_json::to_value(&self.pipeline).unwrap();
+        let mut new = serde_json::to_value(&other.pipeline).unwrap();
+        for value in [&mut old, &mut new] {
+            if let Value::Object(fields) = value {
+                fields.remove("revision");
+            }
+        }
+
+        let mut changes = Vec::new();
+        diff_values(String::new(), Some(&old), Some(&new), &mut changes);
+        changes
+    }
+}
+
+fn diff_values(
+    path: String,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<RevisionChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            for (key, old_value) in old {
+                let path = format!("{path}/{}", escape(key));
+                diff_values(path, Some(old_value), new.get(key), changes);
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    let path = format!("{path}/{}", escape(key));
+                    diff_values(path, None, Some(new_value), changes);
+                }
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            for idx in 0..old.len().max(new.len()) {
+                diff_values(format!("{path}/{idx}"), old.get(idx), new.get(idx), changes);
+            }
+        }
+        (old, new) if old != new => changes.push(RevisionChange {
+            path,
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+/// Escapes a JSON Pointer reference token.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -1,17 +1,29 @@
-use crate::engine::{Engine, EventContext, PipelineContext, StepOutput};
+use crate::engine::{Engine, EventContext, FallbackFrame, PipelineContext, StepOutput};
 use crate::error::EngineError;
+use crate::metrics::PIPELINES_MATCHED;
+use crate::pipeline::dead_letter::{DeadLetter, DeadLetterPayload, DeadLetterQueue, ReplayMode};
+use crate::pipeline::retry::RetryPolicy;
+use crate::pipeline::scheduler::{PipelineScheduler, PipelineTask};
 use crate::pipeline::storage::PipelineStorage;
 use crate::pipeline::Pipeline;
-use crate::recipient::Recipient;
+use crate::recipient::{Recipient, RecipientDirectory, RecipientGroups};
+use crate::recorder::Recorder;
+use crate::step::SerializedStep;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::ops::Range;
 use std::sync::Arc;
 use tokio::task::JoinSet;
-use tracing::error;
+use tracing::{error, instrument, warn, Instrument};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, ToSchema, Debug)]
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
 pub struct ProcessEventRequest {
+    /// Generated if not set. Set it, or `idempotency_key`, so that retried requests
+    /// are recognized as duplicates.
     #[serde(default = "Uuid::now_v7")]
     pub id: Uuid,
     #[serde(default = "Uuid::nil")]
@@ -19,37 +31,270 @@ pub struct ProcessEventRequest {
     pub event: String,
     pub recipient: Option<RecipientSelector>,
     pub context: EventContext,
+    /// Run the pipelines without sending anything, see [`PipelineRunner::dry_run`].
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Events with the same key are processed only once. Defaults to `id`.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+impl ProcessEventRequest {
+    /// Key identifying retries of the same event.
+    pub fn deduplication_key(&self) -> String {
+        match &self.idempotency_key {
+            Some(key) => key.clone(),
+            None => self.id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
 #[serde(rename_all = "snake_case", untagged)]
 pub enum RecipientSelector {
     Recipient(Recipient),
+    /// Recipient ID, resolved through the [`RecipientDirectory`].
+    RecipientId(String),
+    Recipients(Vec<Recipient>),
+    RecipientIds(Vec<String>),
+    /// Members of a recipient group, see [`RecipientGroups`].
+    Group {
+        group: String,
+        /// Only members with IDs after this one, used to resume a partially processed event.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after: Option<String>,
+    },
 }
 
+/// Number of group members resolved and processed at once.
+const GROUP_PAGE_SIZE: u64 = 1000;
+
+/// Position in the recipients of an event.
+enum RecipientBatches {
+    Selector(Option<RecipientSelector>),
+    Group {
+        group: String,
+        after: Option<String>,
+    },
+    Done,
+}
+
+impl RecipientBatches {
+    /// Selector of the recipients that have not been processed yet.
+    fn remaining(self) -> Option<RecipientSelector> {
+        match self {
+            RecipientBatches::Selector(recipient_sel) => recipient_sel,
+            RecipientBatches::Group { group, after } => {
+                Some(RecipientSelector::Group { group, after })
+            }
+            RecipientBatches::Done => None,
+        }
+    }
+}
+
+/// How a pipeline run has ended.
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PipelineOutcome {
+    Completed,
+    Interrupted,
+    Suspended {
+        #[schema(value_type = String)]
+        resume_at: DateTime<Utc>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Result of a dry-run of a single pipeline.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DryRunResult {
+    pub pipeline_id: Uuid,
+    /// Steps that would be executed, including the ones added by branching steps.
+    #[schema(value_type = Vec<Object>)]
+    pub steps: Vec<SerializedStep>,
+    pub outcome: PipelineOutcome,
+    #[schema(value_type = Object)]
+    pub context: PipelineContext,
+}
+
+#[derive(Clone)]
 pub struct PipelineRunner {
     pipeline_storage: Arc<dyn PipelineStorage>,
+    scheduler: Arc<dyn PipelineScheduler>,
+    recorder: Arc<dyn Recorder>,
+    recipient_directory: Option<Arc<dyn RecipientDirectory>>,
+    recipient_groups: Option<Arc<dyn RecipientGroups>>,
     engine: Engine,
+    retry_policy: RetryPolicy,
+    dead_letters: Option<Arc<dyn DeadLetterQueue>>,
 }
 
 impl PipelineRunner {
-    pub fn new(pipeline_storage: Arc<dyn PipelineStorage>, engine: Engine) -> Self {
+    pub fn new(
+        pipeline_storage: Arc<dyn PipelineStorage>,
+        scheduler: Arc<dyn PipelineScheduler>,
+        recorder: Arc<dyn Recorder>,
+        recipient_directory: Option<Arc<dyn RecipientDirectory>>,
+        recipient_groups: Option<Arc<dyn RecipientGroups>>,
+        engine: Engine,
+        retry_policy: RetryPolicy,
+    ) -> Self {
         Self {
             pipeline_storage,
+            scheduler,
+            recorder,
+            recipient_directory,
+            recipient_groups,
             engine,
+            retry_policy,
+            dead_letters: None,
         }
     }
 
-    pub async fn process_eventrequest(&self, msg: ProcessEventRequest) {
-        self.process_event(
-            msg.id,
-            msg.project_id,
-            &msg.event,
-            msg.context,
-            msg.recipient,
-        )
-        .await
-        .unwrap()
+    /// Keeps failed events and pipelines in the queue instead of dropping them.
+    pub fn with_dead_letter_queue(mut self, dead_letters: Arc<dyn DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    pub async fn process_eventrequest(&self, mut msg: ProcessEventRequest) {
+        if msg.dry_run {
+            warn!("Dry-run event {} received by a worker, ignoring", msg.id);
+            return;
+        }
+
+        // On failure, the dead letter selects only the recipients that have not been processed
+        let result = self
+            .process_event(
+                msg.id,
+                msg.project_id,
+                &msg.event,
+                msg.context.clone(),
+                &mut msg.recipient,
+            )
+            .await;
+
+        if let Err(err) = result {
+            error!("Failed to process event {}: {:?}", msg.id, err);
+            let letter = DeadLetter::new(
+                msg.project_id,
+                Some(msg.id),
+                format!("{err:?}"),
+                DeadLetterPayload::Event { request: msg },
+            );
+            self.push_dead_letter(letter).await;
+        }
+    }
+
+    /// Stores the dead letter, if the queue is configured. Failures are only logged.
+    pub async fn push_dead_letter(&self, letter: DeadLetter) {
+        let Some(dead_letters) = &self.dead_letters else {
+            return;
+        };
+        let id = letter.id;
+        if let Err(err) = dead_letters.push(letter).await {
+            error!("Failed to store dead letter {id}: {:?}", err);
+        }
+    }
+
+    /// Replays a dead letter. Pipelines are handed over to the scheduler,
+    /// so they are run by the workers rather than by the caller.
+    ///
+    /// Events always go through the current pipelines, as they have failed before
+    /// their pipelines were started. Their recipients are limited to those not processed
+    /// before the failure, e.g. the remaining pages of a group.
+    pub async fn replay(&self, letter: DeadLetter, mode: ReplayMode) -> Result<(), EngineError> {
+        match letter.payload {
+            DeadLetterPayload::Message { body } => {
+                let request: ProcessEventRequest = serde_json::from_str(&body)
+                    .map_err(|e| EngineError::InternalError(Box::new(e)))?;
+                self.schedule_event(request).await
+            }
+            DeadLetterPayload::Event { request } => self.schedule_event(request).await,
+            DeadLetterPayload::Pipeline {
+                pipeline,
+                mut context,
+            } => {
+                let task = match mode {
+                    ReplayMode::Stored => {
+                        context.retry_attempt = 0;
+                        PipelineTask {
+                            pipeline,
+                            context: *context,
+                        }
+                    }
+                    ReplayMode::Current => {
+                        let Some(current) = self
+                            .pipeline_storage
+                            .get_pipeline_by_id(pipeline.id)
+                            .await?
+                        else {
+                            return Err(EngineError::InternalError(
+                                format!("Pipeline {} not found", pipeline.id).into(),
+                            ));
+                        };
+                        let (pipeline, mut restarted) = Self::create_contexts(
+                            context.event_id,
+                            context.project_id,
+                            &context.event_name,
+                            &context.event_context,
+                            &[current.pipeline],
+                            vec![context.recipient],
+                            false,
+                        )
+                        .remove(0);
+                        restarted.notification_id = context.notification_id;
+                        PipelineTask {
+                            pipeline,
+                            context: restarted,
+                        }
+                    }
+                };
+                self.scheduler.schedule(task, Utc::now()).await
+            }
+            DeadLetterPayload::Task { pipeline, context } => {
+                let task = PipelineTask {
+                    pipeline: Pipeline::deserialize(pipeline)
+                        .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+                    context: PipelineContext::deserialize(context)
+                        .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+                };
+                self.scheduler.schedule(task, Utc::now()).await
+            }
+        }
+    }
+
+    /// Schedules the pipelines of the event for every recipient to be started right away.
+    /// Lets services without transport credentials hand events over to the workers.
+    pub async fn schedule_event(&self, msg: ProcessEventRequest) -> Result<(), EngineError> {
+        let pipelines = self
+            .pipeline_storage
+            .get_pipelines_for_event(msg.project_id, &msg.event)
+            .await?;
+        if pipelines.is_empty() {
+            return Ok(());
+        }
+
+        let mut batches = RecipientBatches::Selector(msg.recipient);
+        while let Some(recipients) = self.next_recipients(msg.project_id, &mut batches).await? {
+            let contexts = Self::create_contexts(
+                msg.id,
+                msg.project_id,
+                &msg.event,
+                &msg.context,
+                &pipelines,
+                recipients,
+                false,
+            );
+            for (pipeline, context) in contexts {
+                self.scheduler
+                    .schedule(PipelineTask { pipeline, context }, Utc::now())
+                    .await?;
+            }
+        }
+        Ok(())
     }
 
     /// Processes an event by executing the associated pipelines.
@@ -60,86 +305,890 @@ impl PipelineRunner {
     /// * `trigger_event` - The name of the event that triggered the pipeline execution.
     /// * `event_context` - The contextual information related to the event.
     /// * `recipient_sel` - An optional selector for the recipient of the event.
+    ///   On error, it is updated to select only the recipients whose pipelines
+    ///   have not been started yet.
+    #[instrument(
+        skip_all,
+        fields(%event_id, project = %project_id, event = event_name)
+    )]
     pub async fn process_event(
         &self,
         event_id: Uuid,
         project_id: Uuid,
         event_name: &str,
         event_context: EventContext,
-        recipient_sel: Option<RecipientSelector>,
+        recipient_sel: &mut Option<RecipientSelector>,
     ) -> Result<(), EngineError> {
-        // Fetch the pipelines associated with the project and event
         let pipelines = self
             .pipeline_storage
             .get_pipelines_for_event(project_id, event_name)
             .await?;
+        if pipelines.is_empty() {
+            return Ok(());
+        }
 
-        // Determine the recipient based on the recipient selector
-        let recipient = match recipient_sel {
-            None => None,
-            Some(RecipientSelector::Recipient(recipient)) => Some(recipient),
-        };
+        let mut batches = RecipientBatches::Selector(recipient_sel.take());
+        loop {
+            let recipients = match self.next_recipients(project_id, &mut batches).await {
+                Ok(Some(recipients)) => recipients,
+                Ok(None) => break,
+                Err(err) => {
+                    *recipient_sel = batches.remaining();
+                    return Err(err);
+                }
+            };
+            let mut contexts = Self::create_contexts(
+                event_id,
+                project_id,
+                event_name,
+                &event_context,
+                &pipelines,
+                recipients,
+                false,
+            );
+            metrics::counter!(PIPELINES_MATCHED).increment(contexts.len() as u64);
 
-        // Execute each pipeline in a separate task in parallel
-        let mut join_handles = JoinSet::new();
-        for pipeline in pipelines {
-            let engine = self.engine.clone();
-            let recipient = recipient.clone();
-            let event_context = event_context.clone();
-            let event_name = event_name.to_string();
+            // Higher priorities are started first, but not waited for:
+            // a slow transport must not delay the pipelines of lower priorities
+            contexts.sort_by_key(|(pipeline, _)| Reverse(pipeline.priority));
 
-            let channel = pipeline.channel.clone();
+            // Execute each pipeline in a separate task in parallel
+            let mut join_handles = JoinSet::new();
+            for (pipeline, context) in contexts {
+                let runner = self.clone();
+                join_handles.spawn(
+                    async move {
+                        // Execute each step in the pipeline
+                        runner.execute_pipeline(pipeline, context).await;
+                    }
+                    .in_current_span(),
+                );
+            }
 
-            let contact = recipient
-                .clone()
-                .map(|r| r.get_primary_contact(&channel))
-                .unwrap_or_default();
+            // Wait for the batch to complete before fetching the next one
+            join_handles.join_all().await;
+        }
+        Ok(())
+    }
+
+    /// Runs the pipelines of an event in-process without sending anything.
+    ///
+    /// Transport plugins stop right before the network call, delays are skipped
+    /// and nothing is scheduled or recorded. Returns the final state of every matched pipeline.
+    pub async fn dry_run(
+        &self,
+        msg: ProcessEventRequest,
+    ) -> Result<Vec<DryRunResult>, EngineError> {
+        let pipelines = self
+            .pipeline_storage
+            .get_pipelines_for_event(msg.project_id, &msg.event)
+            .await?;
+
+        let mut results = Vec::new();
+        let mut batches = RecipientBatches::Selector(msg.recipient);
+        while let Some(recipients) = self.next_recipients(msg.project_id, &mut batches).await? {
+            let contexts = Self::create_contexts(
+                msg.id,
+                msg.project_id,
+                &msg.event,
+                &msg.context,
+                &pipelines,
+                recipients,
+                true,
+            );
+
+            for (mut pipeline, mut context) in contexts {
+                let pipeline_id = pipeline.id;
+                let mut steps = Vec::new();
+                let outcome = self
+                    .run_steps(&mut pipeline, &mut context, Some(&mut steps))
+                    .await;
+
+                results.push(DryRunResult {
+                    pipeline_id,
+                    steps,
+                    outcome,
+                    context,
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    /// Pairs every pipeline with a fresh context for every recipient.
+    fn create_contexts(
+        event_id: Uuid,
+        project_id: Uuid,
+        event_name: &str,
+        event_context: &EventContext,
+        pipelines: &[Pipeline],
+        recipients: Vec<Option<Recipient>>,
+        dry_run: bool,
+    ) -> Vec<(Pipeline, PipelineContext)> {
+        let mut result = Vec::with_capacity(pipelines.len() * recipients.len());
+        for recipient in recipients {
+            for pipeline in pipelines.iter().cloned() {
+                let channel = pipeline.channel.clone();
+
+                let contact = recipient
+                    .as_ref()
+                    .and_then(|r| r.get_primary_contact(&channel));
 
-            join_handles.spawn(async move {
                 let context = PipelineContext {
                     step_number: 0,
+                    retry_attempt: 0,
+                    dry_run,
 
                     project_id,
-                    recipient,
-                    event_name,
-                    event_context,
+                    recipient: recipient.clone(),
+                    event_name: event_name.to_string(),
+                    event_context: event_context.clone(),
                     plugin_contexts: Default::default(),
                     messages: Default::default(),
                     channel,
                     contact,
                     notification_id: Uuid::now_v7(),
                     event_id,
+                    pipeline_id: pipeline.id,
+                    pipeline_revision: pipeline.revision,
+                    fallbacks: Default::default(),
+                    credential: None,
+                };
+                result.push((pipeline, context));
+            }
+        }
+        result
+    }
+
+    /// Returns the next batch of recipients, `None` when there are no more.
+    /// Groups are fetched page by page, so that large groups are never loaded at once.
+    /// On error, `batches` is left as is, so that the failed batch can be retried.
+    async fn next_recipients(
+        &self,
+        project_id: Uuid,
+        batches: &mut RecipientBatches,
+    ) -> Result<Option<Vec<Option<Recipient>>>, EngineError> {
+        match batches {
+            RecipientBatches::Done => Ok(None),
+            RecipientBatches::Selector(Some(RecipientSelector::Group { group, after })) => {
+                *batches = RecipientBatches::Group {
+                    group: group.clone(),
+                    after: after.clone(),
+                };
+                Box::pin(self.next_recipients(project_id, batches)).await
+            }
+            RecipientBatches::Selector(recipient_sel) => {
+                let recipients = self
+                    .resolve_recipients(project_id, recipient_sel.clone())
+                    .await?;
+                *batches = RecipientBatches::Done;
+                Ok(Some(recipients))
+            }
+            RecipientBatches::Group { group, after } => {
+                let Some(groups) = &self.recipient_groups else {
+                    warn!("Recipient groups are not configured, skipping group: {group}");
+                    *batches = RecipientBatches::Done;
+                    return Ok(None);
                 };
 
-                // Execute each step in the pipeline
-                Self::execute_pipeline(engine, pipeline, context).await;
-            });
+                let ids = groups
+                    .get_members(project_id, group, after.as_deref(), GROUP_PAGE_SIZE)
+                    .await?;
+                if ids.is_empty() {
+                    *batches = RecipientBatches::Done;
+                    return Ok(None);
+                }
+                let next_page = (ids.len() as u64 == GROUP_PAGE_SIZE).then(|| ids.last().cloned());
+
+                let recipient_sel = Some(RecipientSelector::RecipientIds(ids));
+                let recipients = self.resolve_recipients(project_id, recipient_sel).await?;
+                *batches = match next_page {
+                    Some(after) => RecipientBatches::Group {
+                        group: group.clone(),
+                        after,
+                    },
+                    None => RecipientBatches::Done,
+                };
+                Ok(Some(recipients))
+            }
         }
+    }
 
-        // Wait for all pipelines to complete
-        join_handles.join_all().await;
-        Ok(())
+    /// Determines the recipients based on the recipient selector.
+    /// Without a selector the pipelines run once, without a recipient.
+    /// Recipient IDs that cannot be resolved are skipped.
+    async fn resolve_recipients(
+        &self,
+        project_id: Uuid,
+        recipient_sel: Option<RecipientSelector>,
+    ) -> Result<Vec<Option<Recipient>>, EngineError> {
+        let ids = match recipient_sel {
+            None => return Ok(vec![None]),
+            Some(RecipientSelector::Recipient(recipient)) => return Ok(vec![Some(recipient)]),
+            Some(RecipientSelector::Recipients(recipients)) => {
+                return Ok(recipients.into_iter().map(Some).collect())
+            }
+            Some(RecipientSelector::RecipientId(id)) => vec![id],
+            Some(RecipientSelector::RecipientIds(ids)) => ids,
+            Some(RecipientSelector::Group { group, .. }) => {
+                warn!("Recipient group {group} must be expanded by pages, skipping");
+                return Ok(vec![]);
+            }
+        };
+
+        let Some(directory) = &self.recipient_directory else {
+            warn!("Recipient directory is not configured, skipping recipients: {ids:?}");
+            return Ok(vec![]);
+        };
+
+        let mut recipients = Vec::with_capacity(ids.len());
+        for id in ids {
+            match directory.get_recipient(project_id, &id).await? {
+                Some(recipient) => recipients.push(Some(recipient)),
+                None => warn!("Recipient not found: {id}"),
+            }
+        }
+        Ok(recipients)
     }
 
-    pub async fn execute_pipeline(
-        engine: Engine,
-        pipeline: Pipeline,
-        mut context: PipelineContext,
-    ) {
-        for (step_number, step) in pipeline.steps.iter().enumerate() {
-            if step_number < context.step_number {
-                continue;
+    /// Executes the pipeline starting from `context.step_number`.
+    ///
+    /// If a step fails with a transient error, the pipeline is handed over to the scheduler
+    /// to be resumed from the same step after a backoff delay.
+    /// Steps returning [`StepOutput::Suspend`] are handled the same way, resuming from the next step.
+    /// Steps returned by [`StepOutput::Branch`] are inserted right after the current step,
+    /// so the suspended pipeline keeps the chosen branch.
+    /// If a step fails inside a `core.fallback` branch, the rest of the branch is replaced
+    /// with the next fallback channel.
+    #[instrument(
+        skip_all,
+        fields(
+            pipeline_id = %pipeline.id,
+            event_id = %context.event_id,
+            notification_id = %context.notification_id,
+            project = %context.project_id,
+            channel = %context.channel,
+        )
+    )]
+    pub async fn execute_pipeline(&self, mut pipeline: Pipeline, mut context: PipelineContext) {
+        let outcome = self.run_steps(&mut pipeline, &mut context, None).await;
+
+        match outcome {
+            PipelineOutcome::Suspended { resume_at } => {
+                let task = PipelineTask { pipeline, context };
+                if let Err(err) = self.scheduler.schedule(task, resume_at).await {
+                    error!("Failed to schedule pipeline: {:?}", err);
+                }
+            }
+            PipelineOutcome::Failed { error } => {
+                let step = pipeline.steps.get(context.step_number).cloned();
+                let mut letter = DeadLetter::new(
+                    context.project_id,
+                    Some(context.event_id),
+                    error,
+                    DeadLetterPayload::Pipeline {
+                        pipeline,
+                        context: Box::new(context),
+                    },
+                );
+                letter.step = step;
+                self.push_dead_letter(letter).await;
             }
+            PipelineOutcome::Completed | PipelineOutcome::Interrupted => {}
+        }
+    }
 
-            let result = engine.execute_step(&mut context, step).await;
-            match result {
-                Ok(StepOutput::Continue) => context.step_number += 1,
-                Ok(StepOutput::Interrupt) => break,
-                Err(err) => {
-                    error!("Error executing step: {:?}", err);
-                    break;
+    /// Runs the steps until the pipeline finishes or has to be suspended.
+    /// Executed steps are appended to `trace`, if given.
+    async fn run_steps(
+        &self,
+        pipeline: &mut Pipeline,
+        context: &mut PipelineContext,
+        mut trace: Option<&mut Vec<SerializedStep>>,
+    ) -> PipelineOutcome {
+        loop {
+            // Leave fallback branches that have been completed successfully
+            while context
+                .fallbacks
+                .last()
+                .is_some_and(|frame| context.step_number >= frame.end)
+            {
+                context.fallbacks.pop();
+            }
+
+            let Some(step) = pipeline.steps.get(context.step_number) else {
+                return PipelineOutcome::Completed;
+            };
+            let step_number = context.step_number;
+            if let Some(trace) = trace.as_mut() {
+                trace.push(step.clone());
+            }
+
+            let failure = match self.engine.execute_step(context, step).await {
+                Ok(StepOutput::Continue) => {
+                    context.finish_step();
+                    continue;
+                }
+                Ok(StepOutput::Interrupt) => return PipelineOutcome::Interrupted,
+                Ok(StepOutput::Suspend { resume_at }) => {
+                    context.finish_step();
+                    if context.dry_run {
+                        continue;
+                    }
+                    return PipelineOutcome::Suspended { resume_at };
+                }
+                Ok(StepOutput::Branch(steps)) => {
+                    context.finish_step();
+                    let position = context.step_number;
+                    splice_steps(pipeline, context, position..position, steps);
+                    continue;
+                }
+                Ok(StepOutput::Fallback(branches)) => {
+                    context.finish_step();
+                    context.fallbacks.push(FallbackFrame {
+                        end: context.step_number,
+                        branches: branches.into(),
+                        channel: context.channel.clone(),
+                    });
+                    if self.next_fallback_branch(pipeline, context).await {
+                        continue;
+                    }
+                    "No fallback channel available".to_string()
                 }
+                Err(err) if err.is_transient() && !context.dry_run => {
+                    let policy = match step.retry_policy() {
+                        Ok(policy) => policy.unwrap_or_else(|| self.retry_policy.clone()),
+                        Err(err) => {
+                            error!("Invalid retry policy: {:?}", err);
+                            return PipelineOutcome::Failed {
+                                error: format!("Invalid retry policy: {err:?}"),
+                            };
+                        }
+                    };
+
+                    context.retry_attempt += 1;
+                    match policy.delay(context.retry_attempt) {
+                        Some(delay) => {
+                            warn!(
+                                "Step {step_number} failed, retrying in {delay:?} (attempt {}): {err:?}",
+                                context.retry_attempt
+                            );
+                            return PipelineOutcome::Suspended {
+                                resume_at: Utc::now() + delay,
+                            };
+                        }
+                        None => format!("No more retries left: {err:?}"),
+                    }
+                }
+                Err(err) => format!("{err:?}"),
+            };
+
+            error!("Error executing step {step_number}: {failure}");
+            if !self.fallback_on_failure(pipeline, context, &failure).await {
+                return PipelineOutcome::Failed { error: failure };
+            }
+            context.clear_sent_messages();
+        }
+    }
+
+    /// Replaces the rest of the failed fallback branch with the next channel.
+    /// Goes up to outer `core.fallback` steps if the innermost one has run out of channels.
+    /// Returns `false` if there is nothing to fall back to.
+    async fn fallback_on_failure(
+        &self,
+        pipeline: &mut Pipeline,
+        context: &mut PipelineContext,
+        reason: &str,
+    ) -> bool {
+        while let Some(frame) = context.fallbacks.last() {
+            let end = frame.end;
+            if !context.dry_run {
+                self.recorder.record_channel_skipped(context, reason).await;
+            }
+
+            let position = context.step_number;
+            splice_steps(pipeline, context, position..end, vec![]);
+            if self.next_fallback_branch(pipeline, context).await {
+                return true;
+            }
+            // The branch of the outer frame this one was part of has failed as well
+            if let Some(frame) = context.fallbacks.pop() {
+                context.channel = frame.channel;
             }
         }
+        false
+    }
+
+    /// Inserts the steps of the next fallback channel the recipient has a contact for.
+    async fn next_fallback_branch(
+        &self,
+        pipeline: &mut Pipeline,
+        context: &mut PipelineContext,
+    ) -> bool {
+        loop {
+            let Some(branch) = context
+                .fallbacks
+                .last_mut()
+                .and_then(|frame| frame.branches.pop_front())
+            else {
+                return false;
+            };
+
+            let contact = context
+                .recipient
+                .as_ref()
+                .and_then(|r| r.get_primary_contact(&branch.channel));
+            context.channel = branch.channel;
+
+            let Some(contact) = contact else {
+                if !context.dry_run {
+                    self.recorder
+                        .record_channel_skipped(
+                            context,
+                            "Recipient has no contact for this channel",
+                        )
+                        .await;
+                }
+                continue;
+            };
+            context.contact = Some(contact);
+            // Messages rendered for the previous channel are not reused, the branch renders its own
+            context.messages.clear();
+
+            let position = context.step_number;
+            splice_steps(pipeline, context, position..position, branch.steps);
+            return true;
+        }
+    }
+}
+
+/// Replaces `range` of pipeline steps, keeping the bounds of active fallback branches in sync.
+fn splice_steps(
+    pipeline: &mut Pipeline,
+    context: &mut PipelineContext,
+    range: Range<usize>,
+    steps: Vec<SerializedStep>,
+) {
+    let removed = range.len();
+    let inserted = steps.len();
+    for frame in context.fallbacks.iter_mut() {
+        if frame.end >= range.end {
+            frame.end = frame.end + inserted - removed;
+        }
+    }
+    pipeline.steps.splice(range, steps);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::DigestStore;
+    use crate::engine::{CorePlugin, EnginePlugin};
+    use crate::http::admin::{ListQueryParams, PaginatedResult};
+    use crate::pipeline::revision::PipelineRevision;
+    use crate::pipeline::storage::PipelineResult;
+    use crate::pipeline::Event;
+    use crate::throttle::ThrottleStore;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::borrow::Cow;
+    use std::error::Error;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Returns the same pipelines for every event.
+    #[derive(Default)]
+    struct StubStorage {
+        pipelines: Vec<Pipeline>,
+    }
+
+    #[async_trait]
+    impl PipelineStorage for StubStorage {
+        async fn get_pipelines_for_event(
+            &self,
+            _project: Uuid,
+            _event_name: &str,
+        ) -> Result<Vec<Pipeline>, EngineError> {
+            Ok(self.pipelines.clone())
+        }
+        async fn list_pipelines(
+            &self,
+            _params: ListQueryParams,
+        ) -> Result<PaginatedResult<PipelineResult>, EngineError> {
+            unimplemented!()
+        }
+        async fn get_pipeline_by_id(
+            &self,
+            _id: Uuid,
+        ) -> Result<Option<PipelineResult>, EngineError> {
+            unimplemented!()
+        }
+        async fn create_pipeline(
+            &self,
+            _pipeline: PipelineResult,
+            _author: Option<String>,
+        ) -> Result<PipelineResult, EngineError> {
+            unimplemented!()
+        }
+        async fn update_pipeline(
+            &self,
+            _pipeline: PipelineResult,
+            _author: Option<String>,
+        ) -> Result<PipelineResult, EngineError> {
+            unimplemented!()
+        }
+        async fn assign_events_to_pipeline(
+            &self,
+            _pipeline_id: Uuid,
+            _event_id: Vec<Uuid>,
+        ) -> Result<(), EngineError> {
+            unimplemented!()
+        }
+        async fn assign_event_patterns_to_pipeline(
+            &self,
+            _pipeline_id: Uuid,
+            _patterns: Vec<String>,
+        ) -> Result<(), EngineError> {
+            unimplemented!()
+        }
+        async fn delete_pipeline(&self, _id: Uuid) -> Result<(), EngineError> {
+            unimplemented!()
+        }
+        async fn list_pipeline_revisions(
+            &self,
+            _pipeline_id: Uuid,
+            _params: ListQueryParams,
+        ) -> Result<PaginatedResult<PipelineRevision>, EngineError> {
+            unimplemented!()
+        }
+        async fn get_pipeline_revision(
+            &self,
+            _pipeline_id: Uuid,
+            _revision: i32,
+        ) -> Result<Option<PipelineRevision>, EngineError> {
+            unimplemented!()
+        }
+        async fn list_events(
+            &self,
+            _params: ListQueryParams,
+        ) -> Result<PaginatedResult<Event>, EngineError> {
+            unimplemented!()
+        }
+        async fn get_event_by_id(&self, _id: Uuid) -> Result<Option<Event>, Box<dyn Error>> {
+            unimplemented!()
+        }
+        async fn get_events_matching(
+            &self,
+            _project_id: Uuid,
+            _patterns: &[String],
+        ) -> Result<Vec<Event>, EngineError> {
+            unimplemented!()
+        }
+        async fn get_event_by_name(
+            &self,
+            _project_id: Uuid,
+            _name: &str,
+        ) -> Result<Option<Event>, EngineError> {
+            unimplemented!()
+        }
+        async fn create_event(
+            &self,
+            _project_id: Uuid,
+            _name: &str,
+            _schema: Option<Value>,
+        ) -> Result<Event, Box<dyn Error>> {
+            unimplemented!()
+        }
+        async fn update_event(
+            &self,
+            _id: Uuid,
+            _name: &str,
+            _schema: Option<Option<Value>>,
+        ) -> Result<Event, Box<dyn Error>> {
+            unimplemented!()
+        }
+        async fn delete_event(&self, _id: Uuid) -> Result<(), Box<dyn Error>> {
+            unimplemented!()
+        }
+    }
+
+    struct StubScheduler;
+
+    #[async_trait]
+    impl PipelineScheduler for StubScheduler {
+        async fn schedule(
+            &self,
+            _task: PipelineTask,
+            _resume_at: DateTime<Utc>,
+        ) -> Result<(), EngineError> {
+            Ok(())
+        }
+        async fn take_due(&self, _limit: u64) -> Result<Vec<PipelineTask>, EngineError> {
+            Ok(vec![])
+        }
+    }
+
+    /// Throttling and digests are not used by these tests.
+    struct StubStore;
+
+    #[async_trait]
+    impl ThrottleStore for StubStore {
+        async fn try_acquire(
+            &self,
+            _key: &str,
+            _limit: u32,
+            _window: Duration,
+        ) -> Result<bool, EngineError> {
+            unimplemented!()
+        }
+    }
+
+    #[async_trait]
+    impl DigestStore for StubStore {
+        async fn append(&self, _key: &str, _event: &EventContext) -> Result<bool, EngineError> {
+            unimplemented!()
+        }
+        async fn take(&self, _key: &str) -> Result<Vec<EventContext>, EngineError> {
+            unimplemented!()
+        }
+    }
+
+    /// Keeps the channels of sent messages and of skipped channels.
+    #[derive(Default)]
+    struct TestRecorder {
+        sent: Mutex<Vec<String>>,
+        skipped: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Recorder for TestRecorder {
+        async fn record_message_sent(&self, context: &PipelineContext, _message_id: Uuid) {
+            self.sent.lock().unwrap().push(context.channel.clone());
+        }
+        async fn record_message_failed(
+            &self,
+            _context: &PipelineContext,
+            _message_id: Uuid,
+            _error: &str,
+        ) {
+        }
+        async fn record_channel_skipped(&self, context: &PipelineContext, _reason: &str) {
+            self.skipped.lock().unwrap().push(context.channel.clone());
+        }
+        async fn record_notification_suppressed(&self, _context: &PipelineContext, _reason: &str) {}
+    }
+
+    /// Sends a message through the current channel, unless the channel is in `failing`.
+    struct StubTransport {
+        recorder: Arc<TestRecorder>,
+        failing: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl EnginePlugin for StubTransport {
+        async fn execute_step(
+            &self,
+            context: &mut PipelineContext,
+            _step: &SerializedStep,
+        ) -> Result<StepOutput, EngineError> {
+            let result = match self.failing.contains(&context.channel.as_str()) {
+                true => Err(EngineError::DeliveryFailed("rejected".to_string())),
+                false => Ok(()),
+            };
+            context
+                .record_delivery(self.recorder.as_ref(), Uuid::now_v7(), result)
+                .await?;
+            Ok(StepOutput::Continue)
+        }
+
+        fn steps(&self) -> Vec<Cow<'static, str>> {
+            vec!["stub.send".into()]
+        }
+    }
+
+    fn runner(
+        recorder: Arc<TestRecorder>,
+        failing: Vec<&'static str>,
+        pipelines: Vec<Pipeline>,
+    ) -> PipelineRunner {
+        let mut engine = Engine::new();
+        engine.add_plugin(Arc::new(CorePlugin::new(
+            Arc::new(StubStore),
+            Arc::new(StubStore),
+            recorder.clone(),
+        )));
+        engine.add_plugin(Arc::new(StubTransport {
+            recorder: recorder.clone(),
+            failing,
+        }));
+        PipelineRunner::new(
+            Arc::new(StubStorage { pipelines }),
+            Arc::new(StubScheduler),
+            recorder,
+            None,
+            None,
+            engine,
+            RetryPolicy::default(),
+        )
+    }
+
+    fn pipeline(steps: Value) -> Pipeline {
+        serde_json::from_value(json!({
+            "id": Uuid::now_v7(),
+            "project_id": Uuid::nil(),
+            "channel": "telegram",
+            "steps": steps,
+        }))
+        .unwrap()
+    }
+
+    fn context(pipeline: &Pipeline, channels: &[&str]) -> PipelineContext {
+        let contacts: Vec<Value> = channels
+            .iter()
+            .map(|channel| json!({"type": channel, "value": "test"}))
+            .collect();
+        let recipient: Recipient = serde_json::from_value(json!({"contacts": contacts})).unwrap();
+        let mut contexts = PipelineRunner::create_contexts(
+            Uuid::now_v7(),
+            Uuid::nil(),
+            "test",
+            &EventContext::default(),
+            std::slice::from_ref(pipeline),
+            vec![Some(recipient)],
+            false,
+        );
+        contexts.pop().unwrap().1
+    }
+
+    fn branch(channel: &str, steps: Value) -> Value {
+        json!({"channel": channel, "steps": steps})
+    }
+
+    fn send() -> Value {
+        json!([{"step": "stub.send"}])
+    }
+
+    #[tokio::test]
+    async fn fallback_skips_channel_without_contact() {
+        let recorder = Arc::new(TestRecorder::default());
+        let runner = runner(recorder.clone(), vec![], vec![]);
+        let mut pipeline = pipeline(json!([
+            {"step": "core.fallback", "channels": [branch("telegram", send()), branch("email", send())]},
+            {"step": "stub.send"},
+        ]));
+        let mut context = context(&pipeline, &["email"]);
+
+        let outcome = runner.run_steps(&mut pipeline, &mut context, None).await;
+
+        assert!(matches!(outcome, PipelineOutcome::Completed));
+        assert_eq!(*recorder.skipped.lock().unwrap(), ["telegram"]);
+        // The step after the fallback runs once, in the chosen channel
+        assert_eq!(*recorder.sent.lock().unwrap(), ["email", "email"]);
+        assert!(context.fallbacks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fallback_on_delivery_failure() {
+        let recorder = Arc::new(TestRecorder::default());
+        let runner = runner(recorder.clone(), vec!["telegram"], vec![]);
+        let mut pipeline = pipeline(json!([
+            {"step": "core.fallback", "channels": [branch("telegram", send()), branch("email", send())]},
+        ]));
+        let mut context = context(&pipeline, &["telegram", "email"]);
+
+        let outcome = runner.run_steps(&mut pipeline, &mut context, None).await;
+
+        assert!(matches!(outcome, PipelineOutcome::Completed));
+        assert_eq!(*recorder.skipped.lock().unwrap(), ["telegram"]);
+        assert_eq!(*recorder.sent.lock().unwrap(), ["email"]);
+    }
+
+    #[tokio::test]
+    async fn exhausted_fallback_fails_pipeline() {
+        let recorder = Arc::new(TestRecorder::default());
+        let runner = runner(recorder.clone(), vec!["telegram", "email"], vec![]);
+        let mut pipeline = pipeline(json!([
+            {"step": "core.fallback", "channels": [branch("telegram", send()), branch("email", send())]},
+            {"step": "stub.send"},
+        ]));
+        let mut context = context(&pipeline, &["telegram", "email"]);
+
+        let outcome = runner.run_steps(&mut pipeline, &mut context, None).await;
+
+        assert!(matches!(outcome, PipelineOutcome::Failed { .. }));
+        assert_eq!(*recorder.skipped.lock().unwrap(), ["telegram", "email"]);
+        assert!(recorder.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn nested_fallback_goes_up_to_outer_frame() {
+        let recorder = Arc::new(TestRecorder::default());
+        let runner = runner(recorder.clone(), vec!["sms", "push"], vec![]);
+        let inner = json!([
+            {"step": "core.fallback", "channels": [branch("sms", send()), branch("push", send())]},
+            {"step": "stub.send"},
+        ]);
+        let mut pipeline = pipeline(json!([
+            {"step": "core.fallback", "channels": [branch("telegram", inner), branch("email", send())]},
+            {"step": "stub.send"},
+        ]));
+        let mut context = context(&pipeline, &["telegram", "sms", "push", "email"]);
+
+        let outcome = runner.run_steps(&mut pipeline, &mut context, None).await;
+
+        assert!(matches!(outcome, PipelineOutcome::Completed));
+        // The inner frame runs out of channels, so the outer one moves on to email
+        assert_eq!(
+            *recorder.skipped.lock().unwrap(),
+            ["sms", "push", "telegram"]
+        );
+        assert_eq!(*recorder.sent.lock().unwrap(), ["email", "email"]);
+        assert!(context.fallbacks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failure_outside_fallback_skips_message() {
+        let recorder = Arc::new(TestRecorder::default());
+        let runner = runner(recorder.clone(), vec!["telegram"], vec![]);
+        let mut pipeline = pipeline(json!([{"step": "stub.send"}]));
+        let mut context = context(&pipeline, &["telegram"]);
+
+        let outcome = runner.run_steps(&mut pipeline, &mut context, None).await;
+
+        assert!(matches!(outcome, PipelineOutcome::Completed));
+        assert!(recorder.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn higher_priorities_start_first() {
+        let recorder = Arc::new(TestRecorder::default());
+        let pipelines = [("email", 0), ("telegram", 10), ("sms", 5), ("push", 10)]
+            .into_iter()
+            .map(|(channel, priority)| {
+                let mut pipeline = pipeline(send());
+                pipeline.channel = channel.to_string();
+                pipeline.priority = priority;
+                pipeline
+            })
+            .collect();
+        let runner = runner(recorder.clone(), vec![], pipelines);
+        let recipient = serde_json::from_value(json!({"contacts": []})).unwrap();
+
+        runner
+            .process_event(
+                Uuid::now_v7(),
+                Uuid::nil(),
+                "test",
+                EventContext::default(),
+                &mut Some(RecipientSelector::Recipient(recipient)),
+            )
+            .await
+            .unwrap();
+
+        // Pipelines of the same priority keep their order
+        assert_eq!(
+            *recorder.sent.lock().unwrap(),
+            ["telegram", "push", "sms", "email"]
+        );
     }
 }
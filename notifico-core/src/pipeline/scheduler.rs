@@ -0,0 +1,27 @@
+use crate::engine::PipelineContext;
+use crate::error::EngineError;
+use crate::pipeline::Pipeline;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Suspended pipeline execution. Resumes from `context.step_number`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PipelineTask {
+    pub pipeline: Pipeline,
+    pub context: PipelineContext,
+}
+
+/// Durable storage for pipelines that should be resumed later.
+#[async_trait]
+pub trait PipelineScheduler: Send + Sync {
+    /// Persists the task to be resumed not earlier than `resume_at`.
+    async fn schedule(
+        &self,
+        task: PipelineTask,
+        resume_at: DateTime<Utc>,
+    ) -> Result<(), EngineError>;
+
+    /// Claims up to `limit` tasks that are due. Each task is returned to exactly one caller.
+    async fn take_due(&self, limit: u64) -> Result<Vec<PipelineTask>, EngineError>;
+}
@@ -0,0 +1,45 @@
+use crate::engine::EventContext;
+use jsonschema::Validator;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Problem found in an event context, `path` is a JSON Pointer into the context.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ContextValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+/// Compiled JSON Schema of the context of an event.
+pub struct EventSchema(Validator);
+
+impl EventSchema {
+    /// Fails if `schema` is not a valid JSON Schema, `path` then points into the schema.
+    pub fn new(schema: &Value) -> Result<Self, ContextValidationError> {
+        jsonschema::validator_for(schema)
+            .map(Self)
+            .map_err(|e| ContextValidationError {
+                path: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+    }
+
+    /// Returns every violation of the schema.
+    pub fn validate(&self, context: &EventContext) -> Result<(), Vec<ContextValidationError>> {
+        let instance = Value::Object(context.0.clone());
+        let errors: Vec<_> = self
+            .0
+            .iter_errors(&instance)
+            .map(|e| ContextValidationError {
+                path: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
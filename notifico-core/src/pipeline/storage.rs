@@ -1,8 +1,10 @@
 use crate::error::EngineError;
 use crate::http::admin::{ListQueryParams, PaginatedResult};
+use crate::pipeline::revision::PipelineRevision;
 use crate::pipeline::{Event, Pipeline};
 use async_trait::async_trait;
 use serde::Serialize;
+use serde_json::Value;
 use std::error::Error;
 use uuid::Uuid;
 
@@ -10,10 +12,13 @@ use uuid::Uuid;
 pub struct PipelineResult {
     pub pipeline: Pipeline,
     pub event_ids: Vec<Uuid>,
+    /// Patterns of event names the pipeline is subscribed to, see [`crate::pipeline::pattern`].
+    pub event_patterns: Vec<String>,
 }
 
 #[async_trait]
 pub trait PipelineStorage: Send + Sync {
+    /// Returns enabled pipelines linked to the event or subscribed to a pattern matching its name.
     async fn get_pipelines_for_event(
         &self,
         project: Uuid,
@@ -24,22 +29,72 @@ pub trait PipelineStorage: Send + Sync {
         params: ListQueryParams,
     ) -> Result<PaginatedResult<PipelineResult>, EngineError>;
     async fn get_pipeline_by_id(&self, id: Uuid) -> Result<Option<PipelineResult>, EngineError>;
-    async fn create_pipeline(&self, pipeline: Pipeline) -> Result<Pipeline, EngineError>;
-    async fn update_pipeline(&self, pipeline: Pipeline) -> Result<(), EngineError>;
+    /// Stores the pipeline as revision 1, along with its events and event patterns.
+    async fn create_pipeline(
+        &self,
+        pipeline: PipelineResult,
+        author: Option<String>,
+    ) -> Result<PipelineResult, EngineError>;
+    /// Stores the pipeline as a new revision, along with its events and event patterns.
+    /// Fails with [`EngineError::Conflict`] unless `pipeline.revision` is the current revision.
+    async fn update_pipeline(
+        &self,
+        pipeline: PipelineResult,
+        author: Option<String>,
+    ) -> Result<PipelineResult, EngineError>;
     async fn assign_events_to_pipeline(
         &self,
         pipeline_id: Uuid,
         event_id: Vec<Uuid>,
     ) -> Result<(), EngineError>;
+    /// Replaces the event patterns of the pipeline.
+    async fn assign_event_patterns_to_pipeline(
+        &self,
+        pipeline_id: Uuid,
+        patterns: Vec<String>,
+    ) -> Result<(), EngineError>;
     async fn delete_pipeline(&self, id: Uuid) -> Result<(), EngineError>;
 
+    async fn list_pipeline_revisions(
+        &self,
+        pipeline_id: Uuid,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<PipelineRevision>, EngineError>;
+    async fn get_pipeline_revision(
+        &self,
+        pipeline_id: Uuid,
+        revision: i32,
+    ) -> Result<Option<PipelineRevision>, EngineError>;
+
     async fn list_events(
         &self,
         params: ListQueryParams,
     ) -> Result<PaginatedResult<Event>, EngineError>;
 
     async fn get_event_by_id(&self, id: Uuid) -> Result<Option<Event>, Box<dyn Error>>;
-    async fn create_event(&self, project_id: Uuid, name: &str) -> Result<Event, Box<dyn Error>>;
-    async fn update_event(&self, id: Uuid, name: &str) -> Result<Event, Box<dyn Error>>;
+    /// Returns the registered events of the project any of the patterns currently covers.
+    async fn get_events_matching(
+        &self,
+        project_id: Uuid,
+        patterns: &[String],
+    ) -> Result<Vec<Event>, EngineError>;
+    async fn get_event_by_name(
+        &self,
+        project_id: Uuid,
+        name: &str,
+    ) -> Result<Option<Event>, EngineError>;
+    async fn create_event(
+        &self,
+        project_id: Uuid,
+        name: &str,
+        schema: Option<Value>,
+    ) -> Result<Event, Box<dyn Error>>;
+    /// Renames the event. The schema is replaced if `schema` is set, `Some(None)` removes it.
+    async fn update_event(
+        &self,
+        id: Uuid,
+        name: &str,
+        schema: Option<Option<Value>>,
+    ) -> Result<Event, Box<dyn Error>>;
     async fn delete_event(&self, id: Uuid) -> Result<(), Box<dyn Error>>;
 }
@@ -1,18 +1,38 @@
 use crate::error::EngineError;
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tracing::warn;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
 pub struct Recipient {
     #[serde(default = "Uuid::nil")]
     pub id: Uuid,
     pub contacts: Vec<Contact>,
+    /// IANA time zone, e.g. `Europe/Berlin`. Defaults to UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
 }
 
 impl Recipient {
+    /// Time zone of the recipient. Unknown time zones are treated as UTC.
+    pub fn tz(&self) -> Tz {
+        let Some(timezone) = &self.timezone else {
+            return Tz::UTC;
+        };
+        parse_timezone(timezone).unwrap_or_else(|e| {
+            warn!("Time zone of recipient {}: {e}", self.id);
+            Tz::UTC
+        })
+    }
+
     pub fn get_primary_contact(&self, channel: &str) -> Option<Contact> {
         self.contacts
             .iter()
@@ -21,7 +41,51 @@ impl Recipient {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+/// Parses an IANA time zone, e.g. `Europe/Berlin`.
+pub fn parse_timezone(timezone: &str) -> Result<Tz, String> {
+    timezone
+        .parse()
+        .map_err(|_| format!("unknown time zone: {timezone}"))
+}
+
+/// Daily period when the recipient must not be disturbed, in the recipient time zone.
+/// May span midnight, e.g. from 22:00 to 08:00.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Returns the end of the quiet hours if `now` falls within them.
+    pub fn ends_at(&self, now: DateTime<Tz>) -> Option<DateTime<Utc>> {
+        let time = now.time();
+        let quiet = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        if !quiet {
+            return None;
+        }
+
+        let mut date = now.date_naive();
+        if time >= self.end {
+            date = date.succ_opt()?;
+        }
+        let end = date.and_time(self.end);
+
+        // The end may fall into a DST gap, then the quiet hours end an hour later
+        let tz = now.timezone();
+        let end = tz.from_local_datetime(&end).earliest().or_else(|| {
+            tz.from_local_datetime(&(end + TimeDelta::hours(1)))
+                .earliest()
+        })?;
+        Some(end.with_timezone(&Utc))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
 pub struct Contact(Value);
 
 impl Contact {
@@ -63,6 +127,86 @@ impl MobilePhoneContact {
 }
 
 #[async_trait]
-pub trait RecipientDirectory {
-    async fn get_recipient(&self, project: Uuid, id: &str) -> Option<Recipient>;
+pub trait RecipientDirectory: Send + Sync {
+    /// Returns `None` for unknown recipients. Failures of the directory itself are errors,
+    /// so that the event is not processed without its recipients.
+    async fn get_recipient(
+        &self,
+        project: Uuid,
+        id: &str,
+    ) -> Result<Option<Recipient>, EngineError>;
+}
+
+/// Membership of recipient groups, such as "admins of workspace 42" or "followers of ticket 123".
+#[async_trait]
+pub trait RecipientGroups: Send + Sync {
+    /// Returns up to `limit` recipient IDs of the group members, ordered by ID,
+    /// starting after the `after` ID.
+    async fn get_members(
+        &self,
+        project: Uuid,
+        group: &str,
+        after: Option<&str>,
+        limit: u64,
+    ) -> Result<Vec<String>, EngineError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+        }
+    }
+
+    fn at(tz: Tz, datetime: &str) -> DateTime<Tz> {
+        tz.from_local_datetime(&datetime.parse().unwrap()).unwrap()
+    }
+
+    fn utc(datetime: &str) -> DateTime<Utc> {
+        datetime.parse().unwrap()
+    }
+
+    #[test]
+    fn test_within_day() {
+        let quiet = quiet_hours("12:00:00", "14:00:00");
+        let now = at(Tz::UTC, "2024-05-01T13:00:00");
+        assert_eq!(quiet.ends_at(now), Some(utc("2024-05-01T14:00:00Z")));
+        assert_eq!(quiet.ends_at(at(Tz::UTC, "2024-05-01T14:00:00")), None);
+        assert_eq!(quiet.ends_at(at(Tz::UTC, "2024-05-01T11:59:00")), None);
+    }
+
+    #[test]
+    fn test_across_midnight() {
+        let quiet = quiet_hours("22:00:00", "08:00:00");
+        let tz = Tz::Europe__Berlin;
+        // Before midnight, ends the next day
+        assert_eq!(
+            quiet.ends_at(at(tz, "2024-05-01T23:00:00")),
+            Some(utc("2024-05-02T06:00:00Z"))
+        );
+        // After midnight, ends the same day
+        assert_eq!(
+            quiet.ends_at(at(tz, "2024-05-02T01:00:00")),
+            Some(utc("2024-05-02T06:00:00Z"))
+        );
+        assert_eq!(quiet.ends_at(at(tz, "2024-05-02T12:00:00")), None);
+    }
+
+    #[test]
+    fn test_end_in_dst_gap() {
+        // Clocks in Berlin go from 02:00 to 03:00 on 2024-03-31
+        let quiet = quiet_hours("01:00:00", "02:30:00");
+        let now = at(Tz::Europe__Berlin, "2024-03-31T01:30:00");
+        assert_eq!(quiet.ends_at(now), Some(utc("2024-03-31T01:30:00Z")));
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Berlin"), Ok(Tz::Europe__Berlin));
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
 }
@@ -1,17 +1,21 @@
-use tracing::{error, info};
+use crate::engine::PipelineContext;
+use async_trait::async_trait;
+use tracing::info;
 use uuid::Uuid;
 
+/// Keeps track of deliveries. Metrics and logs of sent and failed messages are emitted
+/// by [`PipelineContext::record_delivery`], which calls the recorder.
+#[async_trait]
 pub trait Recorder: Send + Sync + 'static {
-    fn record_message_sent(&self, event_id: Uuid, notification_id: Uuid, message_id: Uuid);
-    fn record_message_failed(
-        &self,
-        event_id: Uuid,
-        notification_id: Uuid,
-        message_id: Uuid,
-        error: &str,
-    );
+    async fn record_message_sent(&self, context: &PipelineContext, message_id: Uuid);
+    async fn record_message_failed(&self, context: &PipelineContext, message_id: Uuid, error: &str);
+    /// Channel fallback decision: `context.channel` is abandoned for the next fallback channel.
+    async fn record_channel_skipped(&self, context: &PipelineContext, reason: &str);
+    /// Notification is deliberately not sent, e.g. throttled.
+    async fn record_notification_suppressed(&self, context: &PipelineContext, reason: &str);
 }
 
+#[derive(Default)]
 pub struct BaseRecorder {}
 
 impl BaseRecorder {
@@ -20,18 +24,29 @@ impl BaseRecorder {
     }
 }
 
+#[async_trait]
 impl Recorder for BaseRecorder {
-    fn record_message_sent(&self, event_id: Uuid, notification_id: Uuid, message_id: Uuid) {
-        info!("Message sent: {event_id}/{notification_id}/{message_id}");
-    }
+    async fn record_message_sent(&self, _context: &PipelineContext, _message_id: Uuid) {}
 
-    fn record_message_failed(
+    async fn record_message_failed(
         &self,
-        event_id: Uuid,
-        notification_id: Uuid,
-        message_id: Uuid,
-        error: &str,
+        _context: &PipelineContext,
+        _message_id: Uuid,
+        _error: &str,
     ) {
-        error!("Failed to send message: {event_id}/{notification_id}/{message_id} - {error}");
+    }
+
+    async fn record_channel_skipped(&self, context: &PipelineContext, reason: &str) {
+        info!(
+            "Channel skipped: {}/{} - {}: {reason}",
+            context.event_id, context.notification_id, context.channel
+        );
+    }
+
+    async fn record_notification_suppressed(&self, context: &PipelineContext, reason: &str) {
+        info!(
+            "Notification suppressed: {}/{} - {reason}",
+            context.event_id, context.notification_id
+        );
     }
 }
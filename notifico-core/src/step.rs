@@ -1,8 +1,13 @@
 use crate::error::EngineError;
+use crate::pipeline::retry::RetryPolicy;
+use schemars::gen::SchemaSettings;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::borrow::Cow;
 
-#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug, JsonSchema)]
 #[serde(transparent)]
 pub struct SerializedStep(pub serde_json::Map<String, Value>);
 
@@ -17,4 +22,56 @@ impl SerializedStep {
     {
         T::deserialize(self.0).map_err(EngineError::InvalidStep)
     }
+
+    /// Step-specific retry policy, if set with the `retry` key.
+    pub fn retry_policy(&self) -> Result<Option<RetryPolicy>, EngineError> {
+        self.0
+            .get("retry")
+            .map(|v| RetryPolicy::deserialize(v).map_err(EngineError::InvalidStep))
+            .transpose()
+    }
+}
+
+/// Problem found while validating pipeline steps.
+#[derive(Serialize, Clone, Debug)]
+pub struct StepValidationError {
+    /// JSON Pointer to the invalid value, e.g. `/2/credential` for the third step.
+    pub path: String,
+    pub message: String,
+}
+
+/// Splits the schema of an internally tagged step enum into schemas of its variants,
+/// keyed by step name. Besides the fields of the variant, steps may only have a `retry` key.
+pub fn step_schemas<T: JsonSchema>() -> Vec<(Cow<'static, str>, Value)> {
+    let mut generator = SchemaSettings::draft07()
+        .with(|s| s.inline_subschemas = true)
+        .into_generator();
+    let retry = generator.subschema_for::<RetryPolicy>();
+    let root = generator.into_root_schema_for::<T>();
+
+    let variants = match root.schema.subschemas.clone().and_then(|s| s.one_of) {
+        Some(variants) => variants,
+        None => vec![Schema::Object(root.schema)],
+    };
+
+    variants
+        .into_iter()
+        .filter_map(|variant| {
+            let Schema::Object(mut object) = variant else {
+                return None;
+            };
+            let validation = object.object.as_mut()?;
+            let Schema::Object(tag) = validation.properties.get("step")? else {
+                return None;
+            };
+            let name = tag.enum_values.as_ref()?.first()?.as_str()?.to_string();
+
+            // Reject misspelled keys, e.g. `credentail`
+            validation
+                .properties
+                .insert("retry".to_string(), retry.clone());
+            validation.additional_properties = Some(Box::new(Schema::Bool(false)));
+            Some((name.into(), serde_json::to_value(object).unwrap()))
+        })
+        .collect()
 }
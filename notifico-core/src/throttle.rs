@@ -0,0 +1,17 @@
+use crate::error::EngineError;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Counts recent notifications for the `core.throttle` step.
+/// Must be shared by all workers for the limits to hold.
+#[async_trait]
+pub trait ThrottleStore: Send + Sync {
+    /// Registers a hit for `key` unless it already had `limit` hits within the last `window`.
+    /// Returns `false` if the hit is throttled.
+    async fn try_acquire(
+        &self,
+        key: &str,
+        limit: u32,
+        window: Duration,
+    ) -> Result<bool, EngineError>;
+}
@@ -1,13 +1,27 @@
 pub use sea_orm_migration::prelude::*;
 
+#[allow(unused_imports)]
+mod entity;
 mod m20220101_000001_create_table;
+mod m20261017_000001_add_pipeline_settings;
+mod m20261017_000002_create_pipeline_revision_table;
+mod m20261018_000001_add_event_schema;
+mod m20261018_000002_create_pipeline_event_pattern_table;
+mod m20261018_000003_backfill_pipeline_revisions;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261017_000001_add_pipeline_settings::Migration),
+            Box::new(m20261017_000002_create_pipeline_revision_table::Migration),
+            Box::new(m20261018_000001_add_event_schema::Migration),
+            Box::new(m20261018_000002_create_pipeline_event_pattern_table::Migration),
+            Box::new(m20261018_000003_backfill_pipeline_revisions::Migration),
+        ]
     }
 
     fn migration_table_name() -> DynIden {
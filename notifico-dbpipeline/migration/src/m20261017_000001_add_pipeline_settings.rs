@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can add only one column per statement
+        let columns = [
+            string(Pipeline::Name).default("").to_owned(),
+            text(Pipeline::Description).default("").to_owned(),
+            boolean(Pipeline::Enabled).default(true).to_owned(),
+            integer(Pipeline::Priority).default(0).to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Pipeline::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            Pipeline::Name,
+            Pipeline::Description,
+            Pipeline::Enabled,
+            Pipeline::Priority,
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Pipeline::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Pipeline {
+    Table,
+    Name,
+    Description,
+    Enabled,
+    Priority,
+}
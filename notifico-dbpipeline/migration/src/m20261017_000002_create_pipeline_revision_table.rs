@@ -0,0 +1,76 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Pipeline::Table)
+                    .add_column(integer(Pipeline::Revision).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PipelineRevision::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(PipelineRevision::Id))
+                    .col(uuid(PipelineRevision::PipelineId))
+                    .col(integer(PipelineRevision::Revision))
+                    .col(string_null(PipelineRevision::Author))
+                    .col(timestamp_with_time_zone(PipelineRevision::CreatedAt))
+                    .col(json_binary(PipelineRevision::Pipeline))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("idx_u_pipeline_revision")
+                    .table(PipelineRevision::Table)
+                    .col(PipelineRevision::PipelineId)
+                    .col(PipelineRevision::Revision)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PipelineRevision::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Pipeline::Table)
+                    .drop_column(Pipeline::Revision)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Pipeline {
+    Table,
+    Revision,
+}
+
+#[derive(DeriveIden)]
+enum PipelineRevision {
+    Table,
+    Id,
+    PipelineId,
+    Revision,
+    Author,
+    CreatedAt,
+    Pipeline,
+}
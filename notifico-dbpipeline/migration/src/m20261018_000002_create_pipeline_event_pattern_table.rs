@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PipelineEventPattern::Table)
+                    .if_not_exists()
+                    .col(uuid(PipelineEventPattern::PipelineId))
+                    .col(string(PipelineEventPattern::Pattern))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                PipelineEventPattern::Table,
+                                PipelineEventPattern::PipelineId,
+                            )
+                            .to(Pipeline::Table, Pipeline::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Restrict),
+                    )
+                    .index(
+                        Index::create()
+                            .primary()
+                            .name("pk_pipeline_event_pattern")
+                            .table(PipelineEventPattern::Table)
+                            .col(PipelineEventPattern::PipelineId)
+                            .col(PipelineEventPattern::Pattern),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PipelineEventPattern::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PipelineEventPattern {
+    Table,
+    PipelineId,
+    Pattern,
+}
+
+#[derive(DeriveIden)]
+enum Pipeline {
+    Table,
+    Id,
+}
@@ -0,0 +1,57 @@
+use crate::entity::{pipeline, pipeline_revision};
+use sea_orm::prelude::Uuid;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use sea_orm_migration::prelude::*;
+use serde_json::json;
+
+/// Creates the first revision of the pipelines created before revisions were introduced,
+/// so that they can be rolled back to.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        let pipelines = pipeline::Entity::find()
+            .filter(pipeline::Column::Revision.eq(0))
+            .all(db)
+            .await?;
+
+        for pipeline in pipelines {
+            // Same layout as a serialized `notifico_core::pipeline::Pipeline`
+            let snapshot = json!({
+                "id": pipeline.id,
+                "project_id": pipeline.project_id,
+                "name": pipeline.name,
+                "description": pipeline.description,
+                "enabled": pipeline.enabled,
+                "priority": pipeline.priority,
+                "revision": 1,
+                "channel": pipeline.channel,
+                "steps": pipeline.steps,
+            });
+
+            pipeline_revision::ActiveModel {
+                id: Set(Uuid::now_v7()),
+                pipeline_id: Set(pipeline.id),
+                revision: Set(1),
+                author: Set(None),
+                created_at: Set(chrono::Utc::now().fixed_offset()),
+                pipeline: Set(snapshot),
+            }
+            .insert(db)
+            .await?;
+
+            let mut pipeline = pipeline.into_active_model();
+            pipeline.revision = Set(1);
+            pipeline.update(db).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
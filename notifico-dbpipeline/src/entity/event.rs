@@ -9,6 +9,8 @@ pub struct Model {
     pub id: Uuid,
     pub project_id: Uuid,
     pub name: String,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub schema: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
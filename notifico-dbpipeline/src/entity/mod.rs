@@ -5,3 +5,5 @@ pub mod prelude;
 pub mod event;
 pub mod pipeline;
 pub mod pipeline_event_j;
+pub mod pipeline_event_pattern;
+pub mod pipeline_revision;
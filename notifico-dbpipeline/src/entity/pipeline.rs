@@ -11,12 +11,20 @@ pub struct Model {
     pub channel: String,
     #[sea_orm(column_type = "JsonBinary")]
     pub steps: Json,
+    pub name: String,
+    #[sea_orm(column_type = "Text")]
+    pub description: String,
+    pub enabled: bool,
+    pub priority: i32,
+    pub revision: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::pipeline_event_j::Entity")]
     PipelineEventJ,
+    #[sea_orm(has_many = "super::pipeline_event_pattern::Entity")]
+    PipelineEventPattern,
 }
 
 impl Related<super::pipeline_event_j::Entity> for Entity {
@@ -25,6 +33,12 @@ impl Related<super::pipeline_event_j::Entity> for Entity {
     }
 }
 
+impl Related<super::pipeline_event_pattern::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PipelineEventPattern.def()
+    }
+}
+
 impl Related<super::event::Entity> for Entity {
     fn to() -> RelationDef {
         super::pipeline_event_j::Relation::Event.def()
@@ -3,3 +3,5 @@
 pub use super::event::Entity as Event;
 pub use super::pipeline::Entity as Pipeline;
 pub use super::pipeline_event_j::Entity as PipelineEventJ;
+pub use super::pipeline_event_pattern::Entity as PipelineEventPattern;
+pub use super::pipeline_revision::Entity as PipelineRevision;
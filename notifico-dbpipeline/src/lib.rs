@@ -1,33 +1,113 @@
 use async_trait::async_trait;
+use chrono::Utc;
 use migration::{Migrator, MigratorTrait};
+use moka::future::Cache;
 use notifico_core::error::EngineError;
 use notifico_core::http::admin::{ListQueryParams, ListableTrait, PaginatedResult};
+use notifico_core::pipeline::pattern;
+use notifico_core::pipeline::revision::PipelineRevision;
 use notifico_core::pipeline::storage::{PipelineResult, PipelineStorage};
 use notifico_core::pipeline::{Event, Pipeline};
 use sea_orm::prelude::Uuid;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    Set,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, NotSet,
+    PaginatorTrait, QueryFilter, QueryOrder, Set, SqlErr, TransactionTrait,
 };
 use serde::Deserialize;
-use std::collections::HashSet;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[allow(unused_imports)]
 mod entity;
 
+/// How long the event patterns of a project are cached. Pattern changes made through
+/// another instance take effect within this time.
+const PATTERN_CACHE_TTL: Duration = Duration::from_secs(10);
+
 pub struct DbPipelineStorage {
     db: DatabaseConnection,
+    /// Event patterns of every pipeline of a project, as `(pipeline_id, pattern)`.
+    patterns: Cache<Uuid, Arc<Vec<(Uuid, String)>>>,
 }
 
 impl DbPipelineStorage {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            patterns: Cache::builder().time_to_live(PATTERN_CACHE_TTL).build(),
+        }
     }
 
     pub async fn setup(&self) -> anyhow::Result<()> {
         Ok(Migrator::up(&self.db, None).await?)
     }
+
+    /// Returns the event patterns of the pipelines, grouped by pipeline ID.
+    async fn get_event_patterns(
+        &self,
+        pipeline_ids: Vec<Uuid>,
+    ) -> Result<HashMap<Uuid, Vec<String>>, EngineError> {
+        let models = entity::pipeline_event_pattern::Entity::find()
+            .filter(entity::pipeline_event_pattern::Column::PipelineId.is_in(pipeline_ids))
+            .all(&self.db)
+            .await?;
+
+        let mut patterns: HashMap<Uuid, Vec<String>> = HashMap::new();
+        for model in models {
+            patterns
+                .entry(model.pipeline_id)
+                .or_default()
+                .push(model.pattern);
+        }
+        Ok(patterns)
+    }
+
+    /// Returns the event patterns of the pipelines of the project, disabled ones included.
+    async fn get_project_patterns(
+        &self,
+        project: Uuid,
+    ) -> Result<Arc<Vec<(Uuid, String)>>, EngineError> {
+        if let Some(patterns) = self.patterns.get(&project).await {
+            return Ok(patterns);
+        }
+
+        let patterns: Vec<(Uuid, String)> = entity::pipeline_event_pattern::Entity::find()
+            .inner_join(entity::pipeline::Entity)
+            .filter(entity::pipeline::Column::ProjectId.eq(project))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|p| (p.pipeline_id, p.pattern))
+            .collect();
+
+        let patterns = Arc::new(patterns);
+        self.patterns.insert(project, patterns.clone()).await;
+        Ok(patterns)
+    }
+
+    /// Adds the event patterns to pipelines loaded with their linked events.
+    async fn to_results(
+        &self,
+        models: Vec<(entity::pipeline::Model, Vec<entity::event::Model>)>,
+    ) -> Result<Vec<PipelineResult>, EngineError> {
+        let mut patterns = self
+            .get_event_patterns(models.iter().map(|(p, _)| p.id).collect())
+            .await?;
+
+        models
+            .into_iter()
+            .map(|(p, e)| {
+                Ok(PipelineResult {
+                    event_patterns: patterns.remove(&p.id).unwrap_or_default(),
+                    pipeline: p.try_into()?,
+                    event_ids: e.into_iter().map(|e| e.id).collect(),
+                })
+            })
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -38,13 +118,37 @@ impl PipelineStorage for DbPipelineStorage {
         project: Uuid,
         event_name: &str,
     ) -> Result<Vec<Pipeline>, EngineError> {
-        let models = entity::pipeline::Entity::find()
+        let mut models = entity::pipeline::Entity::find()
             .inner_join(entity::event::Entity)
             .filter(entity::pipeline::Column::ProjectId.eq(project))
             .filter(entity::event::Column::Name.eq(event_name))
+            .filter(entity::pipeline::Column::Enabled.eq(true))
+            .order_by_desc(entity::pipeline::Column::Priority)
             .all(&self.db)
             .await?;
 
+        // Patterns of a project are few, so they are matched here rather than in the database
+        let patterns = self.get_project_patterns(project).await?;
+
+        let linked: HashSet<Uuid> = models.iter().map(|m| m.id).collect();
+        let matched: HashSet<Uuid> = patterns
+            .iter()
+            .filter(|(pipeline_id, _)| !linked.contains(pipeline_id))
+            .filter(|(_, pattern)| pattern::matches(pattern, event_name))
+            .map(|(pipeline_id, _)| *pipeline_id)
+            .collect();
+
+        if !matched.is_empty() {
+            models.extend(
+                entity::pipeline::Entity::find()
+                    .filter(entity::pipeline::Column::Id.is_in(matched))
+                    .filter(entity::pipeline::Column::Enabled.eq(true))
+                    .all(&self.db)
+                    .await?,
+            );
+            models.sort_by_key(|m| std::cmp::Reverse(m.priority));
+        }
+
         models.into_iter().map(|m| m.try_into()).collect()
     }
 
@@ -60,19 +164,8 @@ impl PipelineStorage for DbPipelineStorage {
             .all(&self.db)
             .await?;
 
-        let results: Result<Vec<PipelineResult>, EngineError> = events
-            .into_iter()
-            .map(|(p, e)| {
-                Ok(PipelineResult {
-                    pipeline: p.try_into()?,
-                    event_ids: e.into_iter().map(|e| e.id).collect(),
-                })
-            })
-            .collect();
-        let results = results?;
-
         Ok(PaginatedResult {
-            items: results,
+            items: self.to_results(events).await?,
             total_count: entity::pipeline::Entity::find()
                 .apply_filter(&params)
                 .unwrap()
@@ -87,44 +180,70 @@ impl PipelineStorage for DbPipelineStorage {
             .all(&self.db)
             .await?;
 
-        let results: Result<Vec<PipelineResult>, EngineError> = events
-            .into_iter()
-            .map(|(p, e)| {
-                Ok(PipelineResult {
-                    pipeline: p.try_into()?,
-                    event_ids: e.into_iter().map(|e| e.id).collect(),
-                })
-            })
-            .collect();
-        let results = results?;
-
-        Ok(results.first().cloned())
+        Ok(self.to_results(events).await?.into_iter().next())
     }
 
-    async fn create_pipeline(&self, pipeline: Pipeline) -> Result<Pipeline, EngineError> {
-        let pipeline_am = entity::pipeline::ActiveModel {
-            id: Set(pipeline.id),
-            project_id: Set(pipeline.project_id),
-            channel: Set(pipeline.channel.clone()),
-            steps: Set(serde_json::to_value(pipeline.steps.clone()).unwrap()),
-        };
-
-        pipeline_am.insert(&self.db).await?;
+    async fn create_pipeline(
+        &self,
+        mut result: PipelineResult,
+        author: Option<String>,
+    ) -> Result<PipelineResult, EngineError> {
+        let pipeline = &mut result.pipeline;
+        pipeline.revision = 1;
+
+        let txn = self.db.begin().await?;
+        entity::pipeline::ActiveModel::from(&*pipeline)
+            .insert(&txn)
+            .await?;
+        revision_model(pipeline, author).insert(&txn).await?;
+        link_events(&txn, pipeline.id, result.event_ids.clone()).await?;
+        link_event_patterns(&txn, pipeline.id, result.event_patterns.clone()).await?;
+        txn.commit().await?;
 
-        Ok(pipeline)
+        self.patterns.invalidate_all();
+        Ok(result)
     }
 
-    async fn update_pipeline(&self, pipeline: Pipeline) -> Result<(), EngineError> {
-        let pipeline_am = entity::pipeline::ActiveModel {
-            id: Set(pipeline.id),
-            project_id: Set(pipeline.project_id),
-            channel: Set(pipeline.channel),
-            steps: Set(serde_json::to_value(pipeline.steps).unwrap()),
-        };
-
-        pipeline_am.update(&self.db).await?;
+    async fn update_pipeline(
+        &self,
+        mut result: PipelineResult,
+        author: Option<String>,
+    ) -> Result<PipelineResult, EngineError> {
+        let pipeline = &mut result.pipeline;
+
+        let txn = self.db.begin().await?;
+        let current = entity::pipeline::Entity::find_by_id(pipeline.id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(pipeline.id.to_string()))?;
+        if current.revision != pipeline.revision {
+            return Err(EngineError::Conflict(format!(
+                "pipeline {} is at revision {}, not {}",
+                pipeline.id, current.revision, pipeline.revision
+            )));
+        }
+        pipeline.revision = current.revision + 1;
 
-        Ok(())
+        entity::pipeline::ActiveModel::from(&*pipeline)
+            .update(&txn)
+            .await?;
+        // Concurrent updates of the same revision are rejected by the unique index
+        revision_model(pipeline, author)
+            .insert(&txn)
+            .await
+            .map_err(|e| match e.sql_err() {
+                Some(SqlErr::UniqueConstraintViolation(_)) => EngineError::Conflict(format!(
+                    "pipeline {} has been changed concurrently",
+                    pipeline.id
+                )),
+                _ => e.into(),
+            })?;
+        link_events(&txn, pipeline.id, result.event_ids.clone()).await?;
+        link_event_patterns(&txn, pipeline.id, result.event_patterns.clone()).await?;
+        txn.commit().await?;
+
+        self.patterns.invalidate_all();
+        Ok(result)
     }
 
     async fn assign_events_to_pipeline(
@@ -132,39 +251,20 @@ impl PipelineStorage for DbPipelineStorage {
         pipeline_id: Uuid,
         event_id: Vec<Uuid>,
     ) -> Result<(), EngineError> {
-        let current_events = entity::pipeline_event_j::Entity::find()
-            .filter(entity::pipeline_event_j::Column::PipelineId.eq(pipeline_id))
-            .all(&self.db)
-            .await?;
-
-        let current_ids: HashSet<Uuid> = current_events.into_iter().map(|e| e.event_id).collect();
-        let new_ids: HashSet<Uuid> = event_id.into_iter().collect();
-
-        let to_delete: Vec<Uuid> = current_ids.difference(&new_ids).cloned().collect();
-        let to_add: Vec<Uuid> = new_ids.difference(&current_ids).cloned().collect();
-
-        if !to_delete.is_empty() {
-            entity::pipeline_event_j::Entity::delete_many()
-                .filter(entity::pipeline_event_j::Column::PipelineId.eq(pipeline_id))
-                .filter(entity::pipeline_event_j::Column::EventId.is_in(to_delete))
-                .exec(&self.db)
-                .await?;
-        }
-
-        if !to_add.is_empty() {
-            let to_add_am: Vec<entity::pipeline_event_j::ActiveModel> = to_add
-                .into_iter()
-                .map(|event_id| entity::pipeline_event_j::ActiveModel {
-                    pipeline_id: Set(pipeline_id),
-                    event_id: Set(event_id),
-                })
-                .collect();
+        link_events(&self.db, pipeline_id, event_id).await
+    }
 
-            entity::pipeline_event_j::Entity::insert_many(to_add_am)
-                .exec(&self.db)
-                .await?;
-        }
+    async fn assign_event_patterns_to_pipeline(
+        &self,
+        pipeline_id: Uuid,
+        patterns: Vec<String>,
+    ) -> Result<(), EngineError> {
+        let txn = self.db.begin().await?;
+        link_event_patterns(&txn, pipeline_id, patterns).await?;
+        txn.commit().await?;
 
+        // The project of the pipeline is not known here, patterns are rarely changed anyway
+        self.patterns.invalidate_all();
         Ok(())
     }
 
@@ -172,9 +272,54 @@ impl PipelineStorage for DbPipelineStorage {
         entity::pipeline::Entity::delete_by_id(id)
             .exec(&self.db)
             .await?;
+        entity::pipeline_revision::Entity::delete_many()
+            .filter(entity::pipeline_revision::Column::PipelineId.eq(id))
+            .exec(&self.db)
+            .await?;
         Ok(())
     }
 
+    async fn list_pipeline_revisions(
+        &self,
+        pipeline_id: Uuid,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<PipelineRevision>, EngineError> {
+        let models = entity::pipeline_revision::Entity::find()
+            .filter(entity::pipeline_revision::Column::PipelineId.eq(pipeline_id))
+            .order_by_desc(entity::pipeline_revision::Column::Revision)
+            .apply_params(&params)
+            .unwrap()
+            .all(&self.db)
+            .await?;
+
+        Ok(PaginatedResult {
+            items: models
+                .into_iter()
+                .map(PipelineRevision::try_from)
+                .collect::<Result<_, _>>()?,
+            total_count: entity::pipeline_revision::Entity::find()
+                .filter(entity::pipeline_revision::Column::PipelineId.eq(pipeline_id))
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    async fn get_pipeline_revision(
+        &self,
+        pipeline_id: Uuid,
+        revision: i32,
+    ) -> Result<Option<PipelineRevision>, EngineError> {
+        entity::pipeline_revision::Entity::find()
+            .filter(entity::pipeline_revision::Column::PipelineId.eq(pipeline_id))
+            .filter(entity::pipeline_revision::Column::Revision.eq(revision))
+            .one(&self.db)
+            .await?
+            .map(PipelineRevision::try_from)
+            .transpose()
+    }
+
     async fn list_events(
         &self,
         params: ListQueryParams,
@@ -202,10 +347,47 @@ impl PipelineStorage for DbPipelineStorage {
         Ok(model.map(Event::from))
     }
 
+    async fn get_events_matching(
+        &self,
+        project_id: Uuid,
+        patterns: &[String],
+    ) -> Result<Vec<Event>, EngineError> {
+        if patterns.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let models = entity::event::Entity::find()
+            .filter(entity::event::Column::ProjectId.eq(project_id))
+            .order_by_asc(entity::event::Column::Name)
+            .all(&self.db)
+            .await?;
+
+        Ok(models
+            .into_iter()
+            .filter(|e| patterns.iter().any(|p| pattern::matches(p, &e.name)))
+            .map(Event::from)
+            .collect())
+    }
+
+    async fn get_event_by_name(
+        &self,
+        project_id: Uuid,
+        name: &str,
+    ) -> Result<Option<Event>, EngineError> {
+        let model = entity::event::Entity::find()
+            .filter(entity::event::Column::ProjectId.eq(project_id))
+            .filter(entity::event::Column::Name.eq(name))
+            .one(&self.db)
+            .await?;
+
+        Ok(model.map(Event::from))
+    }
+
     async fn create_event(
         &self,
         project_id: Uuid,
         name: &str,
+        schema: Option<Value>,
     ) -> Result<Event, Box<dyn std::error::Error>> {
         let id = Uuid::now_v7();
 
@@ -213,6 +395,7 @@ impl PipelineStorage for DbPipelineStorage {
             id: Set(id),
             project_id: Set(project_id),
             name: Set(name.to_string()),
+            schema: Set(schema.clone()),
         }
         .insert(&self.db)
         .await?;
@@ -221,13 +404,20 @@ impl PipelineStorage for DbPipelineStorage {
             id,
             project_id,
             name: name.to_string(),
+            schema,
         })
     }
 
-    async fn update_event(&self, id: Uuid, name: &str) -> Result<Event, Box<dyn Error>> {
+    async fn update_event(
+        &self,
+        id: Uuid,
+        name: &str,
+        schema: Option<Option<Value>>,
+    ) -> Result<Event, Box<dyn Error>> {
         entity::event::ActiveModel {
             id: Set(id),
             name: Set(name.to_string()),
+            schema: schema.map(Set).unwrap_or(NotSet),
             ..Default::default()
         }
         .update(&self.db)
@@ -254,18 +444,140 @@ impl TryFrom<entity::pipeline::Model> for Pipeline {
         Ok(Self {
             id: value.id,
             project_id: value.project_id,
+            name: value.name,
+            description: value.description,
+            enabled: value.enabled,
+            priority: value.priority,
+            revision: value.revision,
             channel: value.channel,
             steps: Vec::deserialize(value.steps).map_err(EngineError::InvalidStep)?,
         })
     }
 }
 
+impl From<&Pipeline> for entity::pipeline::ActiveModel {
+    fn from(pipeline: &Pipeline) -> Self {
+        Self {
+            id: Set(pipeline.id),
+            project_id: Set(pipeline.project_id),
+            channel: Set(pipeline.channel.clone()),
+            steps: Set(serde_json::to_value(&pipeline.steps).unwrap()),
+            name: Set(pipeline.name.clone()),
+            description: Set(pipeline.description.clone()),
+            enabled: Set(pipeline.enabled),
+            priority: Set(pipeline.priority),
+            revision: Set(pipeline.revision),
+        }
+    }
+}
+
+/// Replaces the events linked to the pipeline.
+async fn link_events(
+    db: &impl ConnectionTrait,
+    pipeline_id: Uuid,
+    event_id: Vec<Uuid>,
+) -> Result<(), EngineError> {
+    let current_events = entity::pipeline_event_j::Entity::find()
+        .filter(entity::pipeline_event_j::Column::PipelineId.eq(pipeline_id))
+        .all(db)
+        .await?;
+
+    let current_ids: HashSet<Uuid> = current_events.into_iter().map(|e| e.event_id).collect();
+    let new_ids: HashSet<Uuid> = event_id.into_iter().collect();
+
+    let to_delete: Vec<Uuid> = current_ids.difference(&new_ids).cloned().collect();
+    let to_add: Vec<Uuid> = new_ids.difference(&current_ids).cloned().collect();
+
+    if !to_delete.is_empty() {
+        entity::pipeline_event_j::Entity::delete_many()
+            .filter(entity::pipeline_event_j::Column::PipelineId.eq(pipeline_id))
+            .filter(entity::pipeline_event_j::Column::EventId.is_in(to_delete))
+            .exec(db)
+            .await?;
+    }
+
+    if !to_add.is_empty() {
+        let to_add_am: Vec<entity::pipeline_event_j::ActiveModel> = to_add
+            .into_iter()
+            .map(|event_id| entity::pipeline_event_j::ActiveModel {
+                pipeline_id: Set(pipeline_id),
+                event_id: Set(event_id),
+            })
+            .collect();
+
+        entity::pipeline_event_j::Entity::insert_many(to_add_am)
+            .exec(db)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the event patterns of the pipeline.
+async fn link_event_patterns(
+    db: &impl ConnectionTrait,
+    pipeline_id: Uuid,
+    patterns: Vec<String>,
+) -> Result<(), EngineError> {
+    let patterns: HashSet<String> = patterns.into_iter().collect();
+
+    entity::pipeline_event_pattern::Entity::delete_many()
+        .filter(entity::pipeline_event_pattern::Column::PipelineId.eq(pipeline_id))
+        .exec(db)
+        .await?;
+
+    if !patterns.is_empty() {
+        let models: Vec<entity::pipeline_event_pattern::ActiveModel> = patterns
+            .into_iter()
+            .map(|pattern| entity::pipeline_event_pattern::ActiveModel {
+                pipeline_id: Set(pipeline_id),
+                pattern: Set(pattern),
+            })
+            .collect();
+
+        entity::pipeline_event_pattern::Entity::insert_many(models)
+            .exec(db)
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn revision_model(
+    pipeline: &Pipeline,
+    author: Option<String>,
+) -> entity::pipeline_revision::ActiveModel {
+    entity::pipeline_revision::ActiveModel {
+        id: Set(Uuid::now_v7()),
+        pipeline_id: Set(pipeline.id),
+        revision: Set(pipeline.revision),
+        author: Set(author),
+        created_at: Set(Utc::now().fixed_offset()),
+        pipeline: Set(serde_json::to_value(pipeline).unwrap()),
+    }
+}
+
+impl TryFrom<entity::pipeline_revision::Model> for PipelineRevision {
+    type Error = EngineError;
+
+    fn try_from(value: entity::pipeline_revision::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pipeline_id: value.pipeline_id,
+            revision: value.revision,
+            author: value.author,
+            created_at: value.created_at.to_utc(),
+            pipeline: Pipeline::deserialize(value.pipeline).map_err(EngineError::InvalidStep)?,
+        })
+    }
+}
+
 impl From<entity::event::Model> for Event {
     fn from(value: entity::event::Model) -> Self {
         Self {
             id: value.id,
             project_id: value.project_id,
             name: value.name,
+            schema: value.schema,
         }
     }
 }
@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeadLetter::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(DeadLetter::Id))
+                    .col(uuid(DeadLetter::ProjectId))
+                    .col(uuid_null(DeadLetter::EventId))
+                    .col(string(DeadLetter::Kind))
+                    .col(text(DeadLetter::Error))
+                    .col(json_binary_null(DeadLetter::Step))
+                    .col(json_binary(DeadLetter::Payload))
+                    .col(timestamp_with_time_zone(DeadLetter::CreatedAt))
+                    .col(timestamp_with_time_zone_null(DeadLetter::ReplayedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_dead_letter_created_at")
+                    .table(DeadLetter::Table)
+                    .col(DeadLetter::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DeadLetter::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DeadLetter {
+    Table,
+    Id,
+    ProjectId,
+    EventId,
+    Kind,
+    Error,
+    Step,
+    Payload,
+    CreatedAt,
+    ReplayedAt,
+}
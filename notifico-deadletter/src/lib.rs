@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use migration::{Migrator, MigratorTrait};
+use notifico_core::error::EngineError;
+use notifico_core::http::admin::{ListQueryParams, ListableTrait, PaginatedResult};
+use notifico_core::pipeline::dead_letter::{DeadLetter, DeadLetterQueue};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, PaginatorTrait, Set};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+
+/// Stores failed events and pipelines in the database.
+pub struct DbDeadLetterQueue {
+    db: DatabaseConnection,
+}
+
+impl DbDeadLetterQueue {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    pub async fn list_dead_letters(
+        &self,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<DeadLetter>, EngineError> {
+        let models = entity::dead_letter::Entity::find()
+            .apply_params(&params)
+            .unwrap()
+            .all(&self.db)
+            .await?;
+
+        Ok(PaginatedResult {
+            items: models
+                .into_iter()
+                .map(DeadLetter::try_from)
+                .collect::<Result<_, _>>()?,
+            total_count: entity::dead_letter::Entity::find()
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    pub async fn get_dead_letter(&self, id: Uuid) -> Result<Option<DeadLetter>, EngineError> {
+        entity::dead_letter::Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .map(DeadLetter::try_from)
+            .transpose()
+    }
+
+    pub async fn mark_replayed(&self, id: Uuid) -> Result<(), EngineError> {
+        entity::dead_letter::ActiveModel {
+            id: Set(id),
+            replayed_at: Set(Some(Utc::now().fixed_offset())),
+            ..Default::default()
+        }
+        .update(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_dead_letter(&self, id: Uuid) -> Result<(), EngineError> {
+        entity::dead_letter::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueue for DbDeadLetterQueue {
+    async fn push(&self, letter: DeadLetter) -> Result<(), EngineError> {
+        entity::dead_letter::ActiveModel {
+            id: Set(letter.id),
+            project_id: Set(letter.project_id),
+            event_id: Set(letter.event_id),
+            kind: Set(letter.payload.kind().to_string()),
+            error: Set(letter.error),
+            step: Set(letter.step.map(|step| serde_json::to_value(step).unwrap())),
+            payload: Set(serde_json::to_value(letter.payload).unwrap()),
+            created_at: Set(letter.created_at.fixed_offset()),
+            replayed_at: Set(letter.replayed_at.map(|t| t.fixed_offset())),
+        }
+        .insert(&self.db)
+        .await?;
+        Ok(())
+    }
+}
+
+impl TryFrom<entity::dead_letter::Model> for DeadLetter {
+    type Error = EngineError;
+
+    fn try_from(value: entity::dead_letter::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id,
+            project_id: value.project_id,
+            event_id: value.event_id,
+            error: value.error,
+            step: value
+                .step
+                .map(Deserialize::deserialize)
+                .transpose()
+                .map_err(EngineError::InvalidStep)?,
+            payload: Deserialize::deserialize(value.payload)
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+            created_at: value.created_at.to_utc(),
+            replayed_at: value.replayed_at.map(|t| t.to_utc()),
+        })
+    }
+}
@@ -0,0 +1,50 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProcessedEvent::Table)
+                    .if_not_exists()
+                    .col(uuid(ProcessedEvent::ProjectId))
+                    .col(string(ProcessedEvent::Key))
+                    .col(timestamp_with_time_zone(ProcessedEvent::ExpiresAt))
+                    .primary_key(
+                        Index::create()
+                            .col(ProcessedEvent::ProjectId)
+                            .col(ProcessedEvent::Key),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_processed_event_expires_at")
+                    .table(ProcessedEvent::Table)
+                    .col(ProcessedEvent::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProcessedEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProcessedEvent {
+    Table,
+    ProjectId,
+    Key,
+    ExpiresAt,
+}
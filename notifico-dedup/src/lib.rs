@@ -0,0 +1,79 @@
+use chrono::{TimeDelta, Utc};
+use migration::{Migrator, MigratorTrait};
+use notifico_core::error::EngineError;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+
+/// Longest TTL of processed event IDs, a leap year.
+pub const MAX_TTL: Duration = Duration::from_secs(366 * 24 * 60 * 60);
+
+/// Remembers processed events for `ttl`, so that retried deliveries
+/// of the same event are not processed twice.
+/// Safe to use from multiple workers: a key is marked as processed by exactly one of them.
+pub struct DbEventDeduplicator {
+    db: DatabaseConnection,
+    ttl: TimeDelta,
+}
+
+impl DbEventDeduplicator {
+    /// `ttl` is capped at [`MAX_TTL`].
+    pub fn new(db: DatabaseConnection, ttl: Duration) -> Self {
+        Self {
+            db,
+            ttl: TimeDelta::seconds(ttl.min(MAX_TTL).as_secs() as i64),
+        }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    /// Marks the event key as processed.
+    /// Returns `false` if it has already been processed within the TTL.
+    pub async fn mark_processed(&self, project_id: Uuid, key: &str) -> Result<bool, EngineError> {
+        let now = Utc::now();
+        let expires_at = now.checked_add_signed(self.ttl).ok_or_else(|| {
+            EngineError::InternalError(format!("dedup TTL {} is out of range", self.ttl).into())
+        })?;
+
+        // An expired record doesn't make the event a duplicate
+        entity::processed_event::Entity::delete_many()
+            .filter(entity::processed_event::Column::ProjectId.eq(project_id))
+            .filter(entity::processed_event::Column::Key.eq(key))
+            .filter(entity::processed_event::Column::ExpiresAt.lte(now.fixed_offset()))
+            .exec(&self.db)
+            .await?;
+
+        let inserted =
+            entity::processed_event::Entity::insert(entity::processed_event::ActiveModel {
+                project_id: Set(project_id),
+                key: Set(key.to_string()),
+                expires_at: Set(expires_at.fixed_offset()),
+            })
+            .on_conflict(
+                OnConflict::columns([
+                    entity::processed_event::Column::ProjectId,
+                    entity::processed_event::Column::Key,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&self.db)
+            .await?;
+        Ok(inserted > 0)
+    }
+
+    /// Deletes expired records. Returns the number of deleted records.
+    pub async fn cleanup(&self) -> Result<u64, EngineError> {
+        let result = entity::processed_event::Entity::delete_many()
+            .filter(entity::processed_event::Column::ExpiresAt.lte(Utc::now().fixed_offset()))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
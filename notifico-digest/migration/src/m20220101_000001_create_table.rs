@@ -0,0 +1,66 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Digest::Table)
+                    .if_not_exists()
+                    .col(string(Digest::Key).primary_key())
+                    .col(timestamp_with_time_zone(Digest::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(DigestEvent::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(DigestEvent::Id))
+                    .col(string(DigestEvent::Key))
+                    .col(json_binary(DigestEvent::Context))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_digest_event_key")
+                    .table(DigestEvent::Table)
+                    .col(DigestEvent::Key)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DigestEvent::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Digest::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Digest {
+    Table,
+    Key,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum DigestEvent {
+    Table,
+    Id,
+    Key,
+    Context,
+}
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use migration::{Migrator, MigratorTrait};
+use notifico_core::digest::DigestStore;
+use notifico_core::engine::EventContext;
+use notifico_core::error::EngineError;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use tracing::error;
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+
+/// Stores pending digests in the database.
+///
+/// Events are stored by the digest key, separately from the digest itself,
+/// so that an event is never lost when it races with a flush:
+/// it either gets into the flushed digest or opens a new one.
+pub struct DbDigestStore {
+    db: DatabaseConnection,
+}
+
+impl DbDigestStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+}
+
+#[async_trait]
+impl DigestStore for DbDigestStore {
+    async fn append(&self, key: &str, event: &EventContext) -> Result<bool, EngineError> {
+        entity::digest_event::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            key: Set(key.to_string()),
+            context: Set(serde_json::to_value(event).unwrap()),
+        }
+        .insert(&self.db)
+        .await?;
+
+        let opened = entity::digest::Entity::insert(entity::digest::ActiveModel {
+            key: Set(key.to_string()),
+            created_at: Set(Utc::now().fixed_offset()),
+        })
+        .on_conflict(
+            OnConflict::column(entity::digest::Column::Key)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&self.db)
+        .await?;
+        Ok(opened > 0)
+    }
+
+    async fn take(&self, key: &str) -> Result<Vec<EventContext>, EngineError> {
+        // Close the digest first: events added from now on open a new one
+        entity::digest::Entity::delete_by_id(key)
+            .exec(&self.db)
+            .await?;
+
+        let models = entity::digest_event::Entity::find()
+            .filter(entity::digest_event::Column::Key.eq(key))
+            .order_by_asc(entity::digest_event::Column::Id)
+            .all(&self.db)
+            .await?;
+        let Some(last) = models.last() else {
+            return Ok(vec![]);
+        };
+
+        entity::digest_event::Entity::delete_many()
+            .filter(entity::digest_event::Column::Key.eq(key))
+            .filter(entity::digest_event::Column::Id.lte(last.id))
+            .exec(&self.db)
+            .await?;
+
+        let mut events = Vec::with_capacity(models.len());
+        for model in models {
+            match serde_json::from_value(model.context) {
+                Ok(event) => events.push(event),
+                Err(e) => error!("Failed to deserialize digest event {}: {e}", model.id),
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::Database;
+    use serde_json::json;
+
+    async fn store() -> DbDigestStore {
+        let store = DbDigestStore::new(Database::connect("sqlite::memory:").await.unwrap());
+        store.setup().await.unwrap();
+        store
+    }
+
+    fn event(n: u32) -> EventContext {
+        serde_json::from_value(json!({ "n": n })).unwrap()
+    }
+
+    fn numbers(events: Vec<EventContext>) -> Vec<u64> {
+        events
+            .iter()
+            .map(|event| event.0["n"].as_u64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn first_event_opens_digest() {
+        let store = store().await;
+
+        assert!(store.append("a", &event(1)).await.unwrap());
+        assert!(!store.append("a", &event(2)).await.unwrap());
+        // Keys are collected separately
+        assert!(store.append("b", &event(3)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn take_returns_events_in_order() {
+        let store = store().await;
+        for n in 1..=3 {
+            store.append("a", &event(n)).await.unwrap();
+        }
+        store.append("b", &event(4)).await.unwrap();
+
+        assert_eq!(numbers(store.take("a").await.unwrap()), vec![1, 2, 3]);
+        assert!(store.take("a").await.unwrap().is_empty());
+        assert_eq!(numbers(store.take("b").await.unwrap()), vec![4]);
+    }
+
+    #[tokio::test]
+    async fn take_closes_digest() {
+        let store = store().await;
+        store.append("a", &event(1)).await.unwrap();
+        store.take("a").await.unwrap();
+
+        // The next event opens a new digest with only itself
+        assert!(store.append("a", &event(2)).await.unwrap());
+        assert_eq!(numbers(store.take("a").await.unwrap()), vec![2]);
+    }
+}
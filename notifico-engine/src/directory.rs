@@ -0,0 +1,64 @@
+use clap::Args;
+use notifico_core::recipient::RecipientDirectory;
+use notifico_recipient::http::{HttpRecipientDirectory, HttpRecipientDirectoryConfig};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// External recipient directory. Recipients are taken from the database if the URL is not set.
+#[derive(Debug, Args)]
+pub struct RecipientDirectoryArgs {
+    /// URL of a recipient, `{project_id}` and `{id}` are substituted
+    #[clap(
+        long = "recipient-directory-url",
+        env = "NOTIFICO_RECIPIENT_DIRECTORY_URL"
+    )]
+    url: Option<String>,
+    /// Value of the Authorization header
+    #[clap(
+        long = "recipient-directory-auth",
+        env = "NOTIFICO_RECIPIENT_DIRECTORY_AUTH"
+    )]
+    auth: Option<String>,
+    /// JSON mapping of the response to a recipient, see `HttpRecipientDirectoryConfig`
+    #[clap(
+        long = "recipient-directory-mapping",
+        env = "NOTIFICO_RECIPIENT_DIRECTORY_MAPPING",
+        value_parser = parse_json
+    )]
+    mapping: Option<Value>,
+    /// Request timeout, in seconds
+    #[clap(
+        long = "recipient-directory-timeout",
+        env = "NOTIFICO_RECIPIENT_DIRECTORY_TIMEOUT",
+        default_value_t = 5
+    )]
+    timeout: u64,
+    /// How long resolved recipients are cached, in seconds
+    #[clap(
+        long = "recipient-directory-cache-ttl",
+        env = "NOTIFICO_RECIPIENT_DIRECTORY_CACHE_TTL",
+        default_value_t = 60
+    )]
+    cache_ttl: u64,
+}
+
+impl RecipientDirectoryArgs {
+    /// Creates the HTTP directory if its URL is set, otherwise returns `database`.
+    pub fn build(self, database: Arc<dyn RecipientDirectory>) -> Arc<dyn RecipientDirectory> {
+        let Some(url) = self.url else {
+            return database;
+        };
+        Arc::new(HttpRecipientDirectory::new(HttpRecipientDirectoryConfig {
+            url,
+            auth: self.auth,
+            mapping: self.mapping,
+            timeout: Duration::from_secs(self.timeout),
+            cache_ttl: Duration::from_secs(self.cache_ttl),
+        }))
+    }
+}
+
+fn parse_json(value: &str) -> Result<Value, serde_json::Error> {
+    serde_json::from_str(value)
+}
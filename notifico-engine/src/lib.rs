@@ -0,0 +1,58 @@
+//! Engine setup shared by Notifico services, so that every service runs pipelines
+//! with the same set of steps and resolves recipients the same way.
+
+pub mod directory;
+
+use notifico_core::credentials::CredentialStorage;
+use notifico_core::digest::DigestStore;
+use notifico_core::engine::{CorePlugin, Engine};
+use notifico_core::recorder::Recorder;
+use notifico_core::throttle::ThrottleStore;
+use notifico_slack::SlackPlugin;
+use notifico_smpp::SmppPlugin;
+use notifico_smtp::EmailPlugin;
+use notifico_subscription::SubscriptionManager;
+use notifico_telegram::TelegramPlugin;
+use notifico_template::source::TemplateSource;
+use notifico_template::Templater;
+use notifico_whatsapp::WaBusinessPlugin;
+use std::sync::Arc;
+
+/// Creates an engine with the core, template, subscription and transport plugins.
+pub fn build_engine(
+    credentials: Arc<dyn CredentialStorage>,
+    recorder: Arc<dyn Recorder>,
+    throttle: Arc<dyn ThrottleStore>,
+    digests: Arc<dyn DigestStore>,
+    templates: Arc<dyn TemplateSource>,
+    subscriptions: Arc<SubscriptionManager>,
+) -> Engine {
+    let mut engine = Engine::new();
+    engine.add_plugin(Arc::new(CorePlugin::new(
+        throttle,
+        digests,
+        recorder.clone(),
+    )));
+    engine.add_plugin(Arc::new(Templater::new(templates)));
+
+    engine.add_plugin(Arc::new(TelegramPlugin::new(
+        credentials.clone(),
+        recorder.clone(),
+    )));
+    engine.add_plugin(Arc::new(EmailPlugin::new(
+        credentials.clone(),
+        recorder.clone(),
+    )));
+    engine.add_plugin(Arc::new(WaBusinessPlugin::new(
+        credentials.clone(),
+        recorder.clone(),
+    )));
+    engine.add_plugin(Arc::new(SmppPlugin::new(
+        credentials.clone(),
+        recorder.clone(),
+    )));
+    engine.add_plugin(Arc::new(SlackPlugin::new(credentials, recorder)));
+
+    engine.add_plugin(subscriptions);
+    engine
+}
@@ -1,13 +1,27 @@
 use backoff::future::retry;
 use backoff::ExponentialBackoff;
+use fe2o3_amqp::types::messaging::{ApplicationProperties, Message};
 use fe2o3_amqp::{Connection, Sender, Session};
 use flume::Receiver;
+use notifico_core::metrics::AMQP_RECONNECTS;
 use notifico_core::pipeline::runner::ProcessEventRequest;
-use tracing::{error, info};
+use tracing::{error, info, info_span, Instrument, Span};
 use url::Url;
 
-pub async fn run(amqp_url: Url, worker_addr: String, event_rx: Receiver<ProcessEventRequest>) {
+/// Event to be sent to the workers, along with the span of the HTTP request that produced it.
+pub struct OutgoingEvent {
+    pub request: ProcessEventRequest,
+    pub span: Span,
+}
+
+pub async fn run(amqp_url: Url, worker_addr: String, event_rx: Receiver<OutgoingEvent>) {
+    let mut reconnect = false;
     'outer: loop {
+        if reconnect {
+            metrics::counter!(AMQP_RECONNECTS).increment(1);
+        }
+        reconnect = true;
+
         info!("Connecting to AMQP broker: {amqp_url}...");
         let connection = retry(ExponentialBackoff::default(), || async {
             Ok(Connection::open("connection-1", amqp_url.clone()).await?)
@@ -45,14 +59,28 @@ pub async fn run(amqp_url: Url, worker_addr: String, event_rx: Receiver<ProcessE
         loop {
             tokio::select! {
                 req = event_rx.recv_async() => {
-                    let Ok(req) = req else {
+                    let Ok(OutgoingEvent { request: req, span }) = req else {
                         error!("Event receiver has been closed");
                         break 'outer;
                     };
-                    info!("Sending event to AMQP: {req:?}...");
+                    let span = info_span!(
+                        parent: &span,
+                        "amqp.send",
+                        event_id = %req.id,
+                        project = %req.project_id,
+                    );
+                    info!(parent: &span, "Sending event to AMQP: {req:?}...");
 
-                    let msg = serde_json::to_string(&req).unwrap();
-                    let _outcome = sender.send(msg).await.unwrap();
+                    // Trace context is passed to the worker in the application properties
+                    let mut properties = ApplicationProperties::builder();
+                    for (key, value) in notifico_telemetry::inject_context(&span) {
+                        properties = properties.insert(key, value);
+                    }
+                    let msg = Message::builder()
+                        .application_properties(properties.build())
+                        .value(serde_json::to_string(&req).unwrap())
+                        .build();
+                    let _outcome = sender.send(msg).instrument(span).await.unwrap();
                 }
                 _ = tokio::signal::ctrl_c() => {
                     info!("Shutting down AMQP session");
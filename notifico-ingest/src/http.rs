@@ -1,22 +1,35 @@
+use crate::amqp::OutgoingEvent;
+use crate::schema::EventValidator;
 use axum::extract::Query;
-use axum::http::StatusCode;
-use axum::routing::post;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 use flume::Sender;
+use metrics_exporter_prometheus::PrometheusHandle;
 use notifico_core::engine::EventContext;
+use notifico_core::metrics::EVENTS_INGESTED;
 use notifico_core::pipeline::runner::ProcessEventRequest;
+use notifico_core::pipeline::schema::ContextValidationError;
 use serde::Deserialize;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::net::TcpListener;
+use tracing::{info_span, Span};
 use utoipa::OpenApi;
 use utoipa_redoc::Redoc;
 use utoipa_redoc::Servable;
 use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
+/// Header with the key of an event, same as `idempotency_key` in the request body.
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
 #[derive(Clone)]
 pub(crate) struct HttpExtensions {
-    pub sender: Sender<ProcessEventRequest>,
+    pub sender: Sender<OutgoingEvent>,
+    /// Not set if the ingest has no database access.
+    pub validator: Option<Arc<EventValidator>>,
+    pub metrics: PrometheusHandle,
 }
 
 #[derive(OpenApi)]
@@ -31,7 +44,10 @@ pub(crate) async fn start(serviceapi_bind: SocketAddr, ext: HttpExtensions) {
     let app = Router::new()
         .route("/v1/send", post(send))
         .route("/v1/send_webhook", post(send_webhook))
-        .layer(Extension(ext.sender));
+        .route("/metrics", get(metrics))
+        .layer(Extension(ext.sender))
+        .layer(Extension(ext.validator))
+        .layer(Extension(ext.metrics));
 
     let app =
         app.merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()));
@@ -40,14 +56,37 @@ pub(crate) async fn start(serviceapi_bind: SocketAddr, ext: HttpExtensions) {
     tokio::spawn(async { axum::serve(listener, app).await.unwrap() });
 }
 
+/// Sends the event to the workers.
+///
+/// Workers process every event only once, recognized by its `id`, or by `idempotency_key`
+/// (also accepted as the `Idempotency-Key` header) if set. Requests without either get
+/// a new `id`, so set one of them to retry requests safely.
 #[utoipa::path(post, path = "/v1/send")]
 async fn send(
-    Extension(sender): Extension<Sender<ProcessEventRequest>>,
-    Json(payload): Json<ProcessEventRequest>,
-) -> StatusCode {
-    sender.send_async(payload).await.unwrap();
+    Extension(sender): Extension<Sender<OutgoingEvent>>,
+    Extension(validator): Extension<Option<Arc<EventValidator>>>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ProcessEventRequest>,
+) -> Result<StatusCode, (StatusCode, Json<Vec<ContextValidationError>>)> {
+    // Dry-runs are served synchronously by the web API
+    if payload.dry_run {
+        return Ok(StatusCode::BAD_REQUEST);
+    }
+
+    if payload.idempotency_key.is_none() {
+        if let Some(key) = headers.get(IDEMPOTENCY_KEY) {
+            let Ok(key) = key.to_str() else {
+                return Ok(StatusCode::BAD_REQUEST);
+            };
+            payload.idempotency_key = Some(key.to_string());
+        }
+    }
+
+    validate(validator.as_deref(), &payload).await?;
+
+    enqueue(&sender, payload).await;
 
-    StatusCode::ACCEPTED
+    Ok(StatusCode::ACCEPTED)
 }
 
 #[derive(Deserialize)]
@@ -57,21 +96,63 @@ struct WebhookParameters {
     event: String,
 }
 
+/// Sends the request body to the workers as the context of `event`.
+/// Every request is a new event, retried requests are not recognized as duplicates.
 #[utoipa::path(post, path = "/v1/send_webhook")]
 async fn send_webhook(
-    Extension(sender): Extension<Sender<ProcessEventRequest>>,
+    Extension(sender): Extension<Sender<OutgoingEvent>>,
+    Extension(validator): Extension<Option<Arc<EventValidator>>>,
     parameters: Query<WebhookParameters>,
     Json(context): Json<EventContext>,
-) -> StatusCode {
+) -> Result<StatusCode, (StatusCode, Json<Vec<ContextValidationError>>)> {
     let process_event_request = ProcessEventRequest {
         id: Uuid::now_v7(),
         project_id: parameters.project_id,
         event: parameters.event.clone(),
         recipient: None,
         context,
+        dry_run: false,
+        idempotency_key: None,
     };
 
-    sender.send_async(process_event_request).await.unwrap();
+    validate(validator.as_deref(), &process_event_request).await?;
+
+    enqueue(&sender, process_event_request).await;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn validate(
+    validator: Option<&EventValidator>,
+    request: &ProcessEventRequest,
+) -> Result<(), (StatusCode, Json<Vec<ContextValidationError>>)> {
+    let Some(validator) = validator else {
+        return Ok(());
+    };
+    validator
+        .validate(request)
+        .await
+        .map_err(|errors| (StatusCode::UNPROCESSABLE_ENTITY, Json(errors)))
+}
+
+/// Hands the event over to the AMQP sender, starting its trace.
+async fn enqueue(sender: &Sender<OutgoingEvent>, request: ProcessEventRequest) {
+    metrics::counter!(EVENTS_INGESTED).increment(1);
+
+    let span = info_span!(
+        parent: Span::current(),
+        "ingest",
+        event_id = %request.id,
+        project = %request.project_id,
+        event = request.event,
+    );
+    sender
+        .send_async(OutgoingEvent { request, span })
+        .await
+        .unwrap();
+}
 
-    StatusCode::ACCEPTED
+/// Metrics in the Prometheus text format.
+async fn metrics(Extension(handle): Extension<PrometheusHandle>) -> String {
+    handle.render()
 }
@@ -1,15 +1,24 @@
 mod amqp;
 mod http;
+mod schema;
 
 use crate::http::HttpExtensions;
+use crate::schema::EventValidator;
 use clap::Parser;
+use notifico_core::db::create_sqlite_if_not_exists;
+use notifico_dbpipeline::DbPipelineStorage;
+use sea_orm::{ConnectOptions, Database};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use url::Url;
 
 #[derive(Parser, Debug)]
 struct Args {
+    /// Database to read event schemas from. Event contexts are not validated if it is not set
+    #[clap(long, env = "NOTIFICO_DB")]
+    db_url: Option<Url>,
     #[clap(long, env = "NOTIFICO_AMQP_URL")]
     amqp: Url,
     #[clap(
@@ -20,6 +29,12 @@ struct Args {
     amqp_addr: String,
     #[clap(long, env = "NOTIFICO_HTTP_INGEST_BIND", default_value = "[::]:8000")]
     bind: SocketAddr,
+    /// OTLP gRPC endpoint to export traces to, e.g. `http://localhost:4317`
+    #[clap(long, env = "NOTIFICO_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<Url>,
+    /// How long event schemas are cached, in seconds
+    #[clap(long, env = "NOTIFICO_EVENT_SCHEMA_CACHE_TTL", default_value_t = 60)]
+    event_schema_cache_ttl: u64,
 }
 
 #[tokio::main]
@@ -32,16 +47,36 @@ async fn main() {
 
     let args = Args::parse();
 
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
+    let _telemetry = notifico_telemetry::init("notifico-ingest", args.otlp_endpoint.as_ref());
 
     info!("Config: {:#?}", args);
 
+    // The schema is managed by the web and the workers, the ingest only reads events
+    let validator = match &args.db_url {
+        Some(db_url) => {
+            create_sqlite_if_not_exists(db_url);
+
+            let mut db_conn_options = ConnectOptions::new(db_url.to_string());
+            db_conn_options.sqlx_logging_level(log::LevelFilter::Debug);
+
+            let db_connection = Database::connect(db_conn_options).await.unwrap();
+            let pipeline_storage = Arc::new(DbPipelineStorage::new(db_connection));
+
+            Some(Arc::new(EventValidator::new(
+                pipeline_storage,
+                Duration::from_secs(args.event_schema_cache_ttl),
+            )))
+        }
+        None => None,
+    };
+
     let (request_tx, request_rx) = flume::bounded(0);
 
-    let ext = HttpExtensions { sender: request_tx };
+    let ext = HttpExtensions {
+        sender: request_tx,
+        validator,
+        metrics: notifico_telemetry::install_metrics_recorder(),
+    };
 
     // Spawns HTTP servers and quits
     http::start(args.bind, ext).await;
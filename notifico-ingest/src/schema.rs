@@ -0,0 +1,75 @@
+use moka::future::Cache;
+use notifico_core::error::EngineError;
+use notifico_core::pipeline::runner::ProcessEventRequest;
+use notifico_core::pipeline::schema::{ContextValidationError, EventSchema};
+use notifico_core::pipeline::storage::PipelineStorage;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Validates event contexts against the schemas of their events.
+/// Schemas are cached, so changes take effect within `cache_ttl`.
+pub struct EventValidator {
+    storage: Arc<dyn PipelineStorage>,
+    cache: Cache<(Uuid, String), Option<Arc<EventSchema>>>,
+}
+
+impl EventValidator {
+    pub fn new(storage: Arc<dyn PipelineStorage>, cache_ttl: Duration) -> Self {
+        Self {
+            storage,
+            cache: Cache::builder().time_to_live(cache_ttl).build(),
+        }
+    }
+
+    /// Events without a schema are accepted as is, including events that are not registered.
+    /// If the schema cannot be loaded, the event is accepted anyway.
+    pub async fn validate(
+        &self,
+        request: &ProcessEventRequest,
+    ) -> Result<(), Vec<ContextValidationError>> {
+        let key = (request.project_id, request.event.clone());
+        let schema = match self.cache.get(&key).await {
+            Some(schema) => schema,
+            None => match self.load(request.project_id, &request.event).await {
+                Ok(schema) => {
+                    self.cache.insert(key, schema.clone()).await;
+                    schema
+                }
+                Err(e) => {
+                    error!("Failed to load schema of event {}: {:?}", request.event, e);
+                    None
+                }
+            },
+        };
+
+        match schema {
+            Some(schema) => schema.validate(&request.context),
+            None => Ok(()),
+        }
+    }
+
+    async fn load(
+        &self,
+        project_id: Uuid,
+        event: &str,
+    ) -> Result<Option<Arc<EventSchema>>, EngineError> {
+        let Some(schema) = self
+            .storage
+            .get_event_by_name(project_id, event)
+            .await?
+            .and_then(|event| event.schema)
+        else {
+            return Ok(None);
+        };
+
+        match EventSchema::new(&schema) {
+            Ok(schema) => Ok(Some(Arc::new(schema))),
+            Err(e) => {
+                warn!("Ignoring invalid schema of event {event}: {}", e.message);
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+pub use sea_orm_migration::prelude::*;
+
+mod m20220101_000001_create_table;
+mod m20261017_000001_create_group_tables;
+mod m20261017_000002_add_recipient_time_zone;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261017_000001_create_group_tables::Migration),
+            Box::new(m20261017_000002_add_recipient_time_zone::Migration),
+        ]
+    }
+
+    fn migration_table_name() -> DynIden {
+        Alias::new("recipient_migrations").into_iden()
+    }
+}
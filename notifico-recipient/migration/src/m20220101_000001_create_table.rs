@@ -0,0 +1,45 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Recipient::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(Recipient::Id))
+                    .col(uuid(Recipient::ProjectId))
+                    .col(json_binary(Recipient::Contacts))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_recipient_project_id")
+                    .table(Recipient::Table)
+                    .col(Recipient::ProjectId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Recipient::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Recipient {
+    Table,
+    Id,
+    ProjectId,
+    Contacts,
+}
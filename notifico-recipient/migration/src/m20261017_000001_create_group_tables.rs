@@ -0,0 +1,73 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecipientGroup::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(RecipientGroup::Id))
+                    .col(uuid(RecipientGroup::ProjectId))
+                    .col(string(RecipientGroup::Name))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_recipient_group_name")
+                    .table(RecipientGroup::Table)
+                    .col(RecipientGroup::ProjectId)
+                    .col(RecipientGroup::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecipientGroupMember::Table)
+                    .if_not_exists()
+                    .col(uuid(RecipientGroupMember::GroupId))
+                    .col(string(RecipientGroupMember::RecipientId))
+                    .primary_key(
+                        Index::create()
+                            .col(RecipientGroupMember::GroupId)
+                            .col(RecipientGroupMember::RecipientId),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecipientGroupMember::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(RecipientGroup::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RecipientGroup {
+    Table,
+    Id,
+    ProjectId,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum RecipientGroupMember {
+    Table,
+    GroupId,
+    RecipientId,
+}
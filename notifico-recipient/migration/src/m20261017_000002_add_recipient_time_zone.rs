@@ -0,0 +1,53 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can add only one column per statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Recipient::Table)
+                    .add_column(string_null(Recipient::Timezone))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Recipient::Table)
+                    .add_column(json_binary_null(Recipient::QuietHours))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Recipient::Table)
+                    .drop_column(Recipient::QuietHours)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Recipient::Table)
+                    .drop_column(Recipient::Timezone)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Recipient {
+    Table,
+    Timezone,
+    QuietHours,
+}
@@ -0,0 +1,304 @@
+use async_trait::async_trait;
+use moka::future::Cache;
+use notifico_core::error::EngineError;
+use notifico_core::recipient::{Recipient, RecipientDirectory};
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct HttpRecipientDirectoryConfig {
+    /// URL of the recipient, `{project_id}` and `{id}` are substituted.
+    pub url: String,
+    /// Value of the `Authorization` header.
+    pub auth: Option<String>,
+    /// Maps the response to a [`Recipient`]. Strings starting with `/` are JSON Pointers
+    /// into the response, e.g. `{"contacts": [{"type": "email", "address": "/email"}]}`.
+    /// Contacts with unresolved pointers are dropped.
+    /// Without a mapping, the response must be a [`Recipient`] itself.
+    pub mapping: Option<Value>,
+    pub timeout: Duration,
+    pub cache_ttl: Duration,
+}
+
+/// Resolves recipients from an external user service.
+pub struct HttpRecipientDirectory {
+    client: Client,
+    config: HttpRecipientDirectoryConfig,
+    cache: Cache<(Uuid, String), Option<Recipient>>,
+}
+
+impl HttpRecipientDirectory {
+    pub fn new(config: HttpRecipientDirectoryConfig) -> Self {
+        Self {
+            client: Client::builder().timeout(config.timeout).build().unwrap(),
+            cache: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(config.cache_ttl)
+                .build(),
+            config,
+        }
+    }
+
+    async fn fetch(&self, project: Uuid, id: &str) -> Result<Option<Recipient>, reqwest::Error> {
+        let url = self
+            .config
+            .url
+            .replace("{project_id}", &project.to_string())
+            .replace("{id}", &urlencode(id));
+
+        let mut request = self.client.get(url);
+        if let Some(auth) = &self.config.auth {
+            request = request.header(AUTHORIZATION, auth);
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response: Value = response.error_for_status()?.json().await?;
+
+        let recipient = match &self.config.mapping {
+            Some(mapping) => map_response(mapping, &response),
+            None => response,
+        };
+        match Recipient::deserialize(recipient) {
+            Ok(mut recipient) => {
+                if recipient.id.is_nil() {
+                    recipient.id = Uuid::parse_str(id).unwrap_or_default();
+                }
+                Ok(Some(recipient))
+            }
+            Err(e) => {
+                error!("Invalid recipient {id} returned by the directory: {e}");
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RecipientDirectory for HttpRecipientDirectory {
+    async fn get_recipient(
+        &self,
+        project: Uuid,
+        id: &str,
+    ) -> Result<Option<Recipient>, EngineError> {
+        let key = (project, id.to_string());
+        if let Some(recipient) = self.cache.get(&key).await {
+            return Ok(recipient);
+        }
+
+        match self.fetch(project, id).await {
+            Ok(recipient) => {
+                self.cache.insert(key, recipient.clone()).await;
+                Ok(recipient)
+            }
+            Err(e) => {
+                error!("Failed to get recipient {id}: {e}");
+                if is_transient(&e) {
+                    Err(EngineError::TransientError(e.to_string()))
+                } else {
+                    Err(EngineError::InternalError(Box::new(e)))
+                }
+            }
+        }
+    }
+}
+
+fn map_response(mapping: &Value, response: &Value) -> Value {
+    match mapping {
+        Value::String(s) if s.starts_with('/') => {
+            response.pointer(s).cloned().unwrap_or(Value::Null)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| map_response(item, response))
+                .filter(|item| !has_nulls(item))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), map_response(v, response)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Objects with unresolved fields, e.g. a contact the user doesn't have.
+fn has_nulls(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Object(fields) => fields.values().any(Value::is_null),
+        _ => false,
+    }
+}
+
+/// Network failures, timeouts, rate limits and server errors are worth retrying.
+fn is_transient(e: &reqwest::Error) -> bool {
+    match e.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => e.is_timeout() || e.is_connect() || e.is_request(),
+    }
+}
+
+/// Encodes a path segment. Form encoding turns spaces into `+`, which paths don't decode.
+fn urlencode(s: &str) -> String {
+    url::form_urlencoded::byte_serialize(s.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::http::HeaderMap;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    #[derive(Default)]
+    struct Stub {
+        requests: AtomicUsize,
+    }
+
+    async fn user(
+        State(stub): State<Arc<Stub>>,
+        Path((project, id)): Path<(Uuid, String)>,
+        headers: HeaderMap,
+    ) -> Result<Json<Value>, axum::http::StatusCode> {
+        stub.requests.fetch_add(1, Ordering::SeqCst);
+        match id.as_str() {
+            "missing" => Err(axum::http::StatusCode::NOT_FOUND),
+            "broken" => Err(axum::http::StatusCode::BAD_GATEWAY),
+            "slow" => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Err(axum::http::StatusCode::NOT_FOUND)
+            }
+            _ => Ok(Json(json!({
+                "project": project,
+                "id": id,
+                "auth": headers.get("authorization").and_then(|v| v.to_str().ok()),
+                "email": "user@example.com",
+            }))),
+        }
+    }
+
+    async fn directory(mapping: Value) -> (HttpRecipientDirectory, Arc<Stub>) {
+        let stub = Arc::new(Stub::default());
+        let app = Router::new()
+            .route("/projects/:project/users/:id", get(user))
+            .with_state(stub.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let directory = HttpRecipientDirectory::new(HttpRecipientDirectoryConfig {
+            url: format!("http://{addr}/projects/{{project_id}}/users/{{id}}"),
+            auth: Some("Bearer secret".to_string()),
+            mapping: Some(mapping),
+            timeout: Duration::from_millis(200),
+            cache_ttl: Duration::from_secs(60),
+        });
+        (directory, stub)
+    }
+
+    /// Echoes request details into contacts, so that they can be checked.
+    fn echo_mapping() -> Value {
+        json!({
+            "contacts": [
+                {"type": "email", "address": "/email"},
+                {"type": "echo", "project": "/project", "id": "/id", "auth": "/auth"},
+                {"type": "telegram", "chat_id": "/telegram_id"},
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn substitutes_url_and_sends_auth() {
+        let (directory, _) = directory(echo_mapping()).await;
+        let project = Uuid::now_v7();
+
+        let recipient = directory
+            .get_recipient(project, "a b/c")
+            .await
+            .unwrap()
+            .unwrap();
+        let echo = recipient.get_primary_contact("echo").unwrap().into_json();
+        assert_eq!(echo["project"], json!(project));
+        assert_eq!(echo["id"], json!("a b/c"));
+        assert_eq!(echo["auth"], json!("Bearer secret"));
+    }
+
+    #[tokio::test]
+    async fn maps_response() {
+        let (directory, _) = directory(echo_mapping()).await;
+        let id = Uuid::now_v7();
+
+        let recipient = directory
+            .get_recipient(Uuid::nil(), &id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(recipient.id, id);
+        assert_eq!(
+            recipient.get_primary_contact("email").unwrap().into_json(),
+            json!({"type": "email", "address": "user@example.com"})
+        );
+        // The user has no Telegram ID
+        assert!(recipient.get_primary_contact("telegram").is_none());
+    }
+
+    #[tokio::test]
+    async fn caches_recipients() {
+        let (directory, stub) = directory(echo_mapping()).await;
+
+        for _ in 0..2 {
+            directory.get_recipient(Uuid::nil(), "user").await.unwrap();
+            directory
+                .get_recipient(Uuid::nil(), "missing")
+                .await
+                .unwrap();
+        }
+        assert_eq!(stub.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_recipient_is_none() {
+        let (directory, _) = directory(echo_mapping()).await;
+
+        let recipient = directory.get_recipient(Uuid::nil(), "missing").await;
+        assert!(matches!(recipient, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_transient() {
+        let (directory, stub) = directory(echo_mapping()).await;
+
+        for _ in 0..2 {
+            let recipient = directory.get_recipient(Uuid::nil(), "broken").await;
+            assert!(matches!(recipient, Err(EngineError::TransientError(_))));
+        }
+        // Errors are not cached
+        assert_eq!(stub.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_transient() {
+        let (directory, _) = directory(echo_mapping()).await;
+
+        let recipient = directory.get_recipient(Uuid::nil(), "slow").await;
+        assert!(matches!(recipient, Err(EngineError::TransientError(_))));
+    }
+}
@@ -0,0 +1,405 @@
+use async_trait::async_trait;
+use migration::{Migrator, MigratorTrait};
+use notifico_core::error::EngineError;
+use notifico_core::http::admin::{ListQueryParams, ListableTrait, PaginatedResult};
+use notifico_core::recipient::{
+    Contact, QuietHours, Recipient, RecipientDirectory, RecipientGroups,
+};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+pub mod http;
+
+/// Recipient stored in the directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecipientItem {
+    #[serde(default = "Uuid::nil")]
+    pub id: Uuid,
+    #[serde(default = "Uuid::nil")]
+    pub project_id: Uuid,
+    pub contacts: Vec<Contact>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Named group of recipients, e.g. followers of a ticket.
+/// Members are recipient IDs, which don't have to be stored in this directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecipientGroupItem {
+    #[serde(default = "Uuid::nil")]
+    pub id: Uuid,
+    #[serde(default = "Uuid::nil")]
+    pub project_id: Uuid,
+    pub name: String,
+}
+
+/// Stores recipients and their contacts in the database,
+/// so that events can reference recipients by ID.
+pub struct DbRecipientDirectory {
+    db: DatabaseConnection,
+}
+
+impl DbRecipientDirectory {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    pub async fn list_recipients(
+        &self,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<RecipientItem>, EngineError> {
+        Ok(PaginatedResult {
+            items: entity::recipient::Entity::find()
+                .apply_params(&params)
+                .unwrap()
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(RecipientItem::try_from)
+                .collect::<Result<_, _>>()?,
+            total_count: entity::recipient::Entity::find()
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    pub async fn get_recipient_by_id(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<RecipientItem>, EngineError> {
+        entity::recipient::Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .map(RecipientItem::try_from)
+            .transpose()
+    }
+
+    /// Stores a new recipient. A nil `id` is replaced with a generated one.
+    pub async fn create_recipient(
+        &self,
+        mut item: RecipientItem,
+    ) -> Result<RecipientItem, EngineError> {
+        if item.id.is_nil() {
+            item.id = Uuid::now_v7();
+        }
+
+        entity::recipient::ActiveModel {
+            id: Set(item.id),
+            project_id: Set(item.project_id),
+            contacts: Set(serde_json::to_value(&item.contacts).unwrap()),
+            timezone: Set(item.timezone.clone()),
+            quiet_hours: Set(item.quiet_hours.map(|q| serde_json::to_value(q).unwrap())),
+        }
+        .insert(&self.db)
+        .await?;
+        Ok(item)
+    }
+
+    pub async fn update_recipient(&self, item: RecipientItem) -> Result<(), EngineError> {
+        entity::recipient::ActiveModel {
+            id: Set(item.id),
+            project_id: Set(item.project_id),
+            contacts: Set(serde_json::to_value(&item.contacts).unwrap()),
+            timezone: Set(item.timezone.clone()),
+            quiet_hours: Set(item.quiet_hours.map(|q| serde_json::to_value(q).unwrap())),
+        }
+        .update(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_recipient(&self, id: Uuid) -> Result<(), EngineError> {
+        entity::recipient::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_groups(
+        &self,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<RecipientGroupItem>, EngineError> {
+        Ok(PaginatedResult {
+            items: entity::recipient_group::Entity::find()
+                .apply_params(&params)
+                .unwrap()
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(RecipientGroupItem::from)
+                .collect(),
+            total_count: entity::recipient_group::Entity::find()
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    pub async fn get_group_by_id(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<RecipientGroupItem>, EngineError> {
+        Ok(entity::recipient_group::Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .map(RecipientGroupItem::from))
+    }
+
+    /// Stores a new group. A nil `id` is replaced with a generated one.
+    pub async fn create_group(
+        &self,
+        mut item: RecipientGroupItem,
+    ) -> Result<RecipientGroupItem, EngineError> {
+        if item.id.is_nil() {
+            item.id = Uuid::now_v7();
+        }
+
+        entity::recipient_group::ActiveModel {
+            id: Set(item.id),
+            project_id: Set(item.project_id),
+            name: Set(item.name.clone()),
+        }
+        .insert(&self.db)
+        .await?;
+        Ok(item)
+    }
+
+    pub async fn update_group(&self, item: RecipientGroupItem) -> Result<(), EngineError> {
+        entity::recipient_group::ActiveModel {
+            id: Set(item.id),
+            project_id: Set(item.project_id),
+            name: Set(item.name),
+        }
+        .update(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes the group along with its membership.
+    pub async fn delete_group(&self, id: Uuid) -> Result<(), EngineError> {
+        entity::recipient_group_member::Entity::delete_many()
+            .filter(entity::recipient_group_member::Column::GroupId.eq(id))
+            .exec(&self.db)
+            .await?;
+        entity::recipient_group::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_group_by_name(
+        &self,
+        project_id: Uuid,
+        name: &str,
+    ) -> Result<Option<RecipientGroupItem>, EngineError> {
+        Ok(entity::recipient_group::Entity::find()
+            .filter(entity::recipient_group::Column::ProjectId.eq(project_id))
+            .filter(entity::recipient_group::Column::Name.eq(name))
+            .one(&self.db)
+            .await?
+            .map(RecipientGroupItem::from))
+    }
+
+    /// Returns the group with the given name, creating it if it doesn't exist.
+    pub async fn get_or_create_group(
+        &self,
+        project_id: Uuid,
+        name: &str,
+    ) -> Result<RecipientGroupItem, EngineError> {
+        if let Some(group) = self.get_group_by_name(project_id, name).await? {
+            return Ok(group);
+        }
+
+        entity::recipient_group::Entity::insert(entity::recipient_group::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            project_id: Set(project_id),
+            name: Set(name.to_string()),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                entity::recipient_group::Column::ProjectId,
+                entity::recipient_group::Column::Name,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&self.db)
+        .await?;
+
+        // Another request might have created the group concurrently
+        Ok(self.get_group_by_name(project_id, name).await?.unwrap())
+    }
+
+    /// Lists recipient IDs of the group members.
+    pub async fn list_members(
+        &self,
+        group_id: Uuid,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<String>, EngineError> {
+        Ok(PaginatedResult {
+            items: entity::recipient_group_member::Entity::find()
+                .filter(entity::recipient_group_member::Column::GroupId.eq(group_id))
+                .apply_params(&params)
+                .unwrap()
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|model| model.recipient_id)
+                .collect(),
+            total_count: entity::recipient_group_member::Entity::find()
+                .filter(entity::recipient_group_member::Column::GroupId.eq(group_id))
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    /// Adds recipients to the group. Existing members are left intact.
+    pub async fn add_members(
+        &self,
+        group_id: Uuid,
+        recipient_ids: Vec<String>,
+    ) -> Result<(), EngineError> {
+        if recipient_ids.is_empty() {
+            return Ok(());
+        }
+
+        let members = recipient_ids.into_iter().map(|recipient_id| {
+            entity::recipient_group_member::ActiveModel {
+                group_id: Set(group_id),
+                recipient_id: Set(recipient_id),
+            }
+        });
+
+        entity::recipient_group_member::Entity::insert_many(members)
+            .on_conflict(
+                OnConflict::columns([
+                    entity::recipient_group_member::Column::GroupId,
+                    entity::recipient_group_member::Column::RecipientId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_members(
+        &self,
+        group_id: Uuid,
+        recipient_ids: Vec<String>,
+    ) -> Result<(), EngineError> {
+        entity::recipient_group_member::Entity::delete_many()
+            .filter(entity::recipient_group_member::Column::GroupId.eq(group_id))
+            .filter(entity::recipient_group_member::Column::RecipientId.is_in(recipient_ids))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RecipientDirectory for DbRecipientDirectory {
+    async fn get_recipient(
+        &self,
+        project: Uuid,
+        id: &str,
+    ) -> Result<Option<Recipient>, EngineError> {
+        let Ok(id) = Uuid::parse_str(id) else {
+            return Ok(None);
+        };
+
+        let Some(model) = entity::recipient::Entity::find_by_id(id)
+            .filter(entity::recipient::Column::ProjectId.eq(project))
+            .one(&self.db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let item = RecipientItem::try_from(model)?;
+        Ok(Some(Recipient {
+            id: item.id,
+            contacts: item.contacts,
+            timezone: item.timezone,
+            quiet_hours: item.quiet_hours,
+        }))
+    }
+}
+
+#[async_trait]
+impl RecipientGroups for DbRecipientDirectory {
+    async fn get_members(
+        &self,
+        project: Uuid,
+        group: &str,
+        after: Option<&str>,
+        limit: u64,
+    ) -> Result<Vec<String>, EngineError> {
+        let Some(group) = self.get_group_by_name(project, group).await? else {
+            return Ok(vec![]);
+        };
+
+        let mut query = entity::recipient_group_member::Entity::find()
+            .filter(entity::recipient_group_member::Column::GroupId.eq(group.id));
+        if let Some(after) = after {
+            query = query.filter(entity::recipient_group_member::Column::RecipientId.gt(after));
+        }
+
+        Ok(query
+            .order_by_asc(entity::recipient_group_member::Column::RecipientId)
+            .limit(limit)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|model| model.recipient_id)
+            .collect())
+    }
+}
+
+impl TryFrom<entity::recipient::Model> for RecipientItem {
+    type Error = EngineError;
+
+    fn try_from(value: entity::recipient::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id,
+            project_id: value.project_id,
+            contacts: serde_json::from_value(value.contacts)
+                .map_err(EngineError::InvalidContactFormat)?,
+            timezone: value.timezone,
+            quiet_hours: value
+                .quiet_hours
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+        })
+    }
+}
+
+impl From<entity::recipient_group::Model> for RecipientGroupItem {
+    fn from(value: entity::recipient_group::Model) -> Self {
+        Self {
+            id: value.id,
+            project_id: value.project_id,
+            name: value.name,
+        }
+    }
+}
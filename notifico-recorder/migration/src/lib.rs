@@ -0,0 +1,20 @@
+pub use sea_orm_migration::prelude::*;
+
+mod m20220101_000001_create_table;
+mod m20261017_000001_add_pipeline_revision;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261017_000001_add_pipeline_revision::Migration),
+        ]
+    }
+
+    fn migration_table_name() -> DynIden {
+        Alias::new("recorder_migrations").into_iden()
+    }
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Delivery::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(Delivery::Id))
+                    .col(uuid(Delivery::ProjectId))
+                    .col(uuid(Delivery::EventId))
+                    .col(uuid(Delivery::NotificationId))
+                    .col(uuid(Delivery::MessageId))
+                    .col(string(Delivery::EventName))
+                    .col(string(Delivery::Channel))
+                    .col(uuid_null(Delivery::RecipientId))
+                    .col(json_binary_null(Delivery::Contact))
+                    .col(string(Delivery::Status))
+                    .col(text_null(Delivery::Error))
+                    .col(timestamp_with_time_zone(Delivery::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_delivery_event_id")
+                    .table(Delivery::Table)
+                    .col(Delivery::EventId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_delivery_recipient_id")
+                    .table(Delivery::Table)
+                    .col(Delivery::ProjectId)
+                    .col(Delivery::RecipientId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Delivery::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Delivery {
+    Table,
+    Id,
+    ProjectId,
+    EventId,
+    NotificationId,
+    MessageId,
+    EventName,
+    Channel,
+    RecipientId,
+    Contact,
+    Status,
+    Error,
+    CreatedAt,
+}
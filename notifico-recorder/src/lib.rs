@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+use migration::{Migrator, MigratorTrait};
+use notifico_core::engine::PipelineContext;
+use notifico_core::error::EngineError;
+use notifico_core::http::admin::{ListQueryParams, ListableTrait, PaginatedResult};
+use notifico_core::recorder::Recorder;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, PaginatorTrait, Set};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info};
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+pub mod entity;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    Failed,
+    Skipped,
+    Suppressed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Skipped => "skipped",
+            DeliveryStatus::Suppressed => "suppressed",
+        }
+    }
+}
+
+/// A single delivery attempt of a message, as stored in the delivery log.
+#[derive(Clone, Debug, Serialize)]
+pub struct Delivery {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub event_id: Uuid,
+    pub notification_id: Uuid,
+    pub message_id: Uuid,
+    pub event_name: String,
+    pub channel: String,
+    pub recipient_id: Option<Uuid>,
+    pub contact: Option<Value>,
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub pipeline_id: Option<Uuid>,
+    pub pipeline_revision: Option<i32>,
+}
+
+/// Recorder that persists every delivery attempt into the database,
+/// so that it can be inspected later through the admin API.
+pub struct DbRecorder {
+    db: DatabaseConnection,
+}
+
+impl DbRecorder {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    async fn record(
+        &self,
+        context: &PipelineContext,
+        message_id: Uuid,
+        status: DeliveryStatus,
+        error: Option<&str>,
+    ) {
+        let model = entity::delivery::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            project_id: Set(context.project_id),
+            event_id: Set(context.event_id),
+            notification_id: Set(context.notification_id),
+            message_id: Set(message_id),
+            event_name: Set(context.event_name.clone()),
+            channel: Set(context.channel.clone()),
+            recipient_id: Set(context.recipient.as_ref().map(|r| r.id)),
+            contact: Set(context.contact.clone().map(|c| c.into_json())),
+            status: Set(status.as_str().to_string()),
+            error: Set(error.map(str::to_string)),
+            created_at: Set(chrono::Utc::now().fixed_offset()),
+            pipeline_id: Set(Some(context.pipeline_id).filter(|id| !id.is_nil())),
+            pipeline_revision: Set(Some(context.pipeline_revision).filter(|&r| r > 0)),
+        };
+
+        if let Err(e) = model.insert(&self.db).await {
+            error!(
+                "Failed to record delivery {}/{}/{message_id}: {e}",
+                context.event_id, context.notification_id
+            );
+        }
+    }
+
+    pub async fn list_deliveries(
+        &self,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<Delivery>, EngineError> {
+        Ok(PaginatedResult {
+            items: entity::delivery::Entity::find()
+                .apply_params(&params)
+                .unwrap()
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(Delivery::from)
+                .collect(),
+            total_count: entity::delivery::Entity::find()
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    pub async fn get_delivery_by_id(&self, id: Uuid) -> Result<Option<Delivery>, EngineError> {
+        Ok(entity::delivery::Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .map(Delivery::from))
+    }
+}
+
+#[async_trait]
+impl Recorder for DbRecorder {
+    async fn record_message_sent(&self, context: &PipelineContext, message_id: Uuid) {
+        self.record(context, message_id, DeliveryStatus::Sent, None)
+            .await
+    }
+
+    async fn record_message_failed(
+        &self,
+        context: &PipelineContext,
+        message_id: Uuid,
+        error: &str,
+    ) {
+        self.record(context, message_id, DeliveryStatus::Failed, Some(error))
+            .await
+    }
+
+    async fn record_channel_skipped(&self, context: &PipelineContext, reason: &str) {
+        info!(
+            "Channel skipped: {}/{} - {}: {reason}",
+            context.event_id, context.notification_id, context.channel
+        );
+        self.record(context, Uuid::nil(), DeliveryStatus::Skipped, Some(reason))
+            .await
+    }
+
+    async fn record_notification_suppressed(&self, context: &PipelineContext, reason: &str) {
+        info!(
+            "Notification suppressed: {}/{} - {reason}",
+            context.event_id, context.notification_id
+        );
+        self.record(
+            context,
+            Uuid::nil(),
+            DeliveryStatus::Suppressed,
+            Some(reason),
+        )
+        .await
+    }
+}
+
+impl From<entity::delivery::Model> for Delivery {
+    fn from(value: entity::delivery::Model) -> Self {
+        Self {
+            id: value.id,
+            project_id: value.project_id,
+            event_id: value.event_id,
+            notification_id: value.notification_id,
+            message_id: value.message_id,
+            event_name: value.event_name,
+            channel: value.channel,
+            recipient_id: value.recipient_id,
+            contact: value.contact,
+            status: value.status,
+            error: value.error,
+            created_at: value.created_at,
+            pipeline_id: value.pipeline_id,
+            pipeline_revision: value.pipeline_revision,
+        }
+    }
+}
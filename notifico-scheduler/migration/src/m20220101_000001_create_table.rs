@@ -0,0 +1,47 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PipelineTask::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(PipelineTask::Id))
+                    .col(timestamp_with_time_zone(PipelineTask::ResumeAt))
+                    .col(json_binary(PipelineTask::Pipeline))
+                    .col(json_binary(PipelineTask::Context))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_pipeline_task_resume_at")
+                    .table(PipelineTask::Table)
+                    .col(PipelineTask::ResumeAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PipelineTask::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PipelineTask {
+    Table,
+    Id,
+    ResumeAt,
+    Pipeline,
+    Context,
+}
@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use migration::{Migrator, MigratorTrait};
+use notifico_core::engine::PipelineContext;
+use notifico_core::error::EngineError;
+use notifico_core::pipeline::dead_letter::{DeadLetter, DeadLetterPayload, DeadLetterQueue};
+use notifico_core::pipeline::scheduler::{PipelineScheduler, PipelineTask};
+use notifico_core::pipeline::Pipeline;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+
+/// Stores suspended pipelines in the database.
+/// Safe to use from multiple workers: every task is claimed by exactly one of them.
+pub struct DbPipelineScheduler {
+    db: DatabaseConnection,
+    dead_letters: Option<Arc<dyn DeadLetterQueue>>,
+}
+
+impl DbPipelineScheduler {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            dead_letters: None,
+        }
+    }
+
+    /// Tasks that cannot be deserialized are moved to the dead letter queue.
+    /// Without it, they are left in place.
+    pub fn with_dead_letter_queue(mut self, dead_letters: Arc<dyn DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    /// Claims the task and stores it as a dead letter.
+    /// The task is put back if the dead letter cannot be stored.
+    async fn move_to_dead_letters(
+        &self,
+        dead_letters: &dyn DeadLetterQueue,
+        model: entity::pipeline_task::Model,
+        error: EngineError,
+    ) -> Result<(), EngineError> {
+        let result = entity::pipeline_task::Entity::delete_by_id(model.id)
+            .exec(&self.db)
+            .await?;
+        if result.rows_affected == 0 {
+            return Ok(());
+        }
+
+        let letter = DeadLetter::new(
+            Uuid::nil(),
+            None,
+            format!("{:?}", error),
+            DeadLetterPayload::Task {
+                pipeline: model.pipeline.clone(),
+                context: model.context.clone(),
+            },
+        );
+        if let Err(err) = dead_letters.push(letter).await {
+            model.into_active_model().insert(&self.db).await?;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PipelineScheduler for DbPipelineScheduler {
+    async fn schedule(
+        &self,
+        task: PipelineTask,
+        resume_at: DateTime<Utc>,
+    ) -> Result<(), EngineError> {
+        entity::pipeline_task::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            resume_at: Set(resume_at.fixed_offset()),
+            pipeline: Set(serde_json::to_value(task.pipeline)
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?),
+            context: Set(serde_json::to_value(task.context)
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?),
+        }
+        .insert(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn take_due(&self, limit: u64) -> Result<Vec<PipelineTask>, EngineError> {
+        let models = entity::pipeline_task::Entity::find()
+            .filter(entity::pipeline_task::Column::ResumeAt.lte(Utc::now().fixed_offset()))
+            .order_by_asc(entity::pipeline_task::Column::ResumeAt)
+            .limit(limit)
+            .all(&self.db)
+            .await?;
+
+        let mut tasks = Vec::with_capacity(models.len());
+        for model in models {
+            // Deserialize before claiming, so that a broken task is not lost
+            match PipelineTask::try_from(model.clone()) {
+                Ok(task) => {
+                    // Claim the task. If another worker has already deleted it, skip.
+                    let result = entity::pipeline_task::Entity::delete_by_id(model.id)
+                        .exec(&self.db)
+                        .await?;
+                    if result.rows_affected > 0 {
+                        tasks.push(task);
+                    }
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to deserialize pipeline task {}: {:?}",
+                        model.id, err
+                    );
+                    if let Some(dead_letters) = &self.dead_letters {
+                        self.move_to_dead_letters(dead_letters.as_ref(), model, err)
+                            .await?;
+                    }
+                }
+            }
+        }
+        Ok(tasks)
+    }
+}
+
+impl TryFrom<entity::pipeline_task::Model> for PipelineTask {
+    type Error = EngineError;
+
+    fn try_from(value: entity::pipeline_task::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pipeline: Pipeline::deserialize(value.pipeline)
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+            context: PipelineContext::deserialize(value.context)
+                .map_err(|e| EngineError::InternalError(Box::new(e)))?,
+        })
+    }
+}
@@ -10,7 +10,7 @@ use jsonwebtoken::{EncodingKey, Header};
 use migration::{Migrator, MigratorTrait};
 use notifico_core::http::admin::{ListQueryParams, ListableTrait};
 use notifico_core::http::auth::Claims;
-use notifico_core::step::SerializedStep;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::{
     engine::PipelineContext,
     engine::{EnginePlugin, StepOutput},
@@ -193,6 +193,10 @@ impl EnginePlugin for SubscriptionManager {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| s.into()).collect()
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
 }
 
 // Implements one-click List-Unsubscribe style URL generation
@@ -0,0 +1,115 @@
+//! Logging, tracing and metrics setup shared by Notifico services.
+//!
+//! Spans are exported over OTLP when an endpoint is configured.
+//! Trace context is carried between services as W3C `traceparent` / `tracestate` values.
+//! Metrics are exported in the Prometheus format.
+
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use url::Url;
+
+/// Flushes pending spans when dropped. Keep it alive until the service exits.
+pub struct TelemetryGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Sets up the global tracing subscriber.
+///
+/// Must be called from within a Tokio runtime if `otlp_endpoint` is set.
+pub fn init(service_name: &'static str, otlp_endpoint: Option<&Url>) -> TelemetryGuard {
+    let provider = otlp_endpoint.map(|endpoint| {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint.as_str())
+            .build()
+            .expect("Failed to create OTLP exporter");
+
+        TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new("service.name", service_name)]))
+            .build()
+    });
+
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(service_name)));
+
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(EnvFilter::from_default_env())
+        .with(otel_layer)
+        .init();
+
+    TelemetryGuard { provider }
+}
+
+/// Serializes the trace context of the span, to be sent along with a message.
+pub fn inject_context(span: &Span) -> HashMap<String, String> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&span.context(), &mut carrier);
+    carrier
+}
+
+/// Makes the span a child of the trace context received with a message.
+pub fn extract_context(span: &Span, carrier: &HashMap<String, String>) {
+    span.set_parent(TraceContextPropagator::new().extract(carrier));
+}
+
+/// Histogram buckets for durations, in seconds.
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+fn prometheus_builder() -> PrometheusBuilder {
+    PrometheusBuilder::new()
+        .set_buckets(DURATION_BUCKETS)
+        .expect("Invalid histogram buckets")
+}
+
+/// Installs the global metrics recorder. The handle renders the Prometheus scrape response,
+/// to be served by the HTTP server of the service.
+pub fn install_metrics_recorder() -> PrometheusHandle {
+    let handle = prometheus_builder()
+        .install_recorder()
+        .expect("Failed to install metrics recorder");
+    notifico_core::metrics::describe();
+
+    let upkeep_handle = handle.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            upkeep_handle.run_upkeep();
+        }
+    });
+    handle
+}
+
+/// Installs the global metrics recorder and serves the metrics on a separate HTTP listener,
+/// for services without an HTTP server of their own.
+pub fn serve_metrics(bind: SocketAddr) {
+    prometheus_builder()
+        .with_http_listener(bind)
+        .install()
+        .expect("Failed to start metrics listener");
+    notifico_core::metrics::describe();
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Template::Table)
+                    .add_column(integer(Template::Revision).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TemplateRevision::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(TemplateRevision::Id))
+                    .col(uuid(TemplateRevision::TemplateId))
+                    .col(integer(TemplateRevision::Revision))
+                    .col(json_binary(TemplateRevision::Template))
+                    .col(timestamp_with_time_zone(TemplateRevision::CreatedAt))
+                    .col(timestamp_with_time_zone_null(TemplateRevision::PublishedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .unique()
+                    .name("idx_u_template_revision")
+                    .table(TemplateRevision::Table)
+                    .col(TemplateRevision::TemplateId)
+                    .col(TemplateRevision::Revision)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TemplateRevision::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Template::Table)
+                    .drop_column(Template::Revision)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Template {
+    Table,
+    Revision,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+enum TemplateRevision {
+    Table,
+    Id,
+    TemplateId,
+    Revision,
+    Template,
+    CreatedAt,
+    PublishedAt,
+}
@@ -1,12 +1,13 @@
 use crate::error::TemplaterError;
-use crate::source::{TemplateItem, TemplateSource};
+use crate::source::{TemplateDraft, TemplateItem, TemplateRevision, TemplateSource};
 use crate::{entity, PreRenderedTemplate, TemplateSelector};
 use async_trait::async_trait;
+use chrono::Utc;
 use migration::{Migrator, MigratorTrait};
 use notifico_core::http::admin::{ListQueryParams, ListableTrait, PaginatedResult};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    Set,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -23,6 +24,88 @@ impl DbTemplateSource {
     pub async fn setup(&self) -> anyhow::Result<()> {
         Ok(Migrator::up(&self.db, None).await?)
     }
+
+    /// Converts templates to items, adding their drafts newer than the published revisions.
+    async fn with_drafts(
+        &self,
+        db: &impl ConnectionTrait,
+        models: Vec<entity::template::Model>,
+    ) -> Result<Vec<TemplateItem>, TemplaterError> {
+        let latest: Vec<(Uuid, Option<i32>)> = entity::template_revision::Entity::find()
+            .select_only()
+            .column(entity::template_revision::Column::TemplateId)
+            .column_as(
+                entity::template_revision::Column::Revision.max(),
+                "revision",
+            )
+            .filter(
+                entity::template_revision::Column::TemplateId
+                    .is_in(models.iter().map(|model| model.id)),
+            )
+            .group_by(entity::template_revision::Column::TemplateId)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let mut condition = Condition::any();
+        let mut has_drafts = false;
+        for (id, revision) in latest {
+            let Some(revision) = revision else {
+                continue;
+            };
+            if models
+                .iter()
+                .any(|model| model.id == id && model.revision < revision)
+            {
+                condition = condition.add(
+                    entity::template_revision::Column::TemplateId
+                        .eq(id)
+                        .and(entity::template_revision::Column::Revision.eq(revision)),
+                );
+                has_drafts = true;
+            }
+        }
+        let mut drafts: HashMap<Uuid, TemplateDraft> = HashMap::new();
+        if has_drafts {
+            for draft in entity::template_revision::Entity::find()
+                .filter(condition)
+                .all(db)
+                .await?
+            {
+                drafts.insert(
+                    draft.template_id,
+                    TemplateDraft {
+                        revision: draft.revision,
+                        template: PreRenderedTemplate(
+                            serde_json::from_value(draft.template).unwrap_or_default(),
+                        ),
+                    },
+                );
+            }
+        }
+
+        Ok(models
+            .into_iter()
+            .map(|model| {
+                let draft = drafts.remove(&model.id);
+                TemplateItem {
+                    draft,
+                    ..model.into()
+                }
+            })
+            .collect())
+    }
+
+    async fn get_template_model(
+        &self,
+        db: &impl ConnectionTrait,
+        id: Uuid,
+    ) -> Result<entity::template::Model, TemplaterError> {
+        entity::template::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or(TemplaterError::TemplateNotFound)
+    }
 }
 
 #[async_trait]
@@ -46,11 +129,9 @@ impl TemplateSource for DbTemplateSource {
     }
 
     async fn get_template_by_id(&self, id: Uuid) -> Result<TemplateItem, TemplaterError> {
-        Ok(entity::template::Entity::find_by_id(id)
-            .one(&self.db)
-            .await?
-            .ok_or(TemplaterError::TemplateNotFound)?
-            .into())
+        let model = self.get_template_model(&self.db, id).await?;
+        let mut items = self.with_drafts(&self.db, vec![model]).await?;
+        Ok(items.remove(0))
     }
 
     async fn list_templates(
@@ -58,16 +139,15 @@ impl TemplateSource for DbTemplateSource {
         channel: &str,
         params: ListQueryParams,
     ) -> Result<PaginatedResult<TemplateItem>, TemplaterError> {
+        let models = entity::template::Entity::find()
+            .apply_params(&params)
+            .unwrap()
+            .filter(entity::template::Column::Channel.eq(channel))
+            .all(&self.db)
+            .await?;
+
         Ok(PaginatedResult {
-            items: entity::template::Entity::find()
-                .apply_params(&params)
-                .unwrap()
-                .filter(entity::template::Column::Channel.eq(channel))
-                .all(&self.db)
-                .await?
-                .into_iter()
-                .map(TemplateItem::from)
-                .collect(),
+            items: self.with_drafts(&self.db, models).await?,
             total_count: entity::template::Entity::find()
                 .apply_filter(&params)
                 .unwrap()
@@ -82,40 +162,200 @@ impl TemplateSource for DbTemplateSource {
         mut item: TemplateItem,
     ) -> Result<TemplateItem, TemplaterError> {
         item.id = Uuid::now_v7();
+        item.revision = 1;
+
+        let txn = self.db.begin().await?;
         entity::template::ActiveModel {
             id: Set(item.id),
             project_id: Set(item.project_id),
             name: Set(item.name.clone()),
             channel: Set(item.channel.clone()),
             template: Set(serde_json::to_value(item.template.clone()).unwrap()),
+            revision: Set(item.revision),
+        }
+        .insert(&txn)
+        .await?;
+
+        let now = Utc::now().fixed_offset();
+        entity::template_revision::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            template_id: Set(item.id),
+            revision: Set(item.revision),
+            template: Set(serde_json::to_value(item.template.clone()).unwrap()),
+            created_at: Set(now),
+            published_at: Set(Some(now)),
         }
-        .insert(&self.db)
+        .insert(&txn)
         .await?;
+        txn.commit().await?;
         Ok(item)
     }
 
     async fn update_template(&self, item: TemplateItem) -> Result<TemplateItem, TemplaterError> {
-        entity::template::ActiveModel {
+        let txn = self.db.begin().await?;
+        let mut current = self.get_template_model(&txn, item.id).await?;
+
+        // Drafts may be newer than the published revision
+        let latest: Option<i32> = entity::template_revision::Entity::find()
+            .select_only()
+            .column_as(
+                entity::template_revision::Column::Revision.max(),
+                "revision",
+            )
+            .filter(entity::template_revision::Column::TemplateId.eq(item.id))
+            .into_tuple()
+            .one(&txn)
+            .await?
+            .flatten();
+
+        let latest = match latest {
+            Some(latest) => latest,
+            None => {
+                // Templates created before versioning have no revisions yet,
+                // keep their content as the first published one
+                let now = Utc::now().fixed_offset();
+                entity::template_revision::ActiveModel {
+                    id: Set(Uuid::now_v7()),
+                    template_id: Set(item.id),
+                    revision: Set(1),
+                    template: Set(current.template.clone()),
+                    created_at: Set(now),
+                    published_at: Set(Some(now)),
+                }
+                .insert(&txn)
+                .await?;
+                current.revision = 1;
+                1
+            }
+        };
+
+        let template = entity::template::ActiveModel {
             id: Set(item.id),
             project_id: Set(item.project_id),
             name: Set(item.name.clone()),
             channel: Set(item.channel.clone()),
+            revision: Set(current.revision),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        entity::template_revision::ActiveModel {
+            id: Set(Uuid::now_v7()),
+            template_id: Set(item.id),
+            revision: Set(latest + 1),
             template: Set(serde_json::to_value(item.template.clone()).unwrap()),
+            created_at: Set(Utc::now().fixed_offset()),
+            published_at: Set(None),
         }
-        .update(&self.db)
+        .insert(&txn)
         .await?;
-        Ok(item)
+        txn.commit().await?;
+
+        Ok(TemplateItem {
+            draft: Some(TemplateDraft {
+                revision: latest + 1,
+                template: item.template,
+            }),
+            ..template.into()
+        })
     }
 
     async fn delete_template(&self, id: Uuid) -> Result<(), TemplaterError> {
+        let txn = self.db.begin().await?;
+        entity::template_revision::Entity::delete_many()
+            .filter(entity::template_revision::Column::TemplateId.eq(id))
+            .exec(&txn)
+            .await?;
         entity::template::ActiveModel {
             id: Set(id),
             ..Default::default()
         }
-        .delete(&self.db)
+        .delete(&txn)
         .await?;
+        txn.commit().await?;
         Ok(())
     }
+
+    async fn list_template_revisions(
+        &self,
+        id: Uuid,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<TemplateRevision>, TemplaterError> {
+        let published = self.get_template_model(&self.db, id).await?.revision;
+
+        Ok(PaginatedResult {
+            items: entity::template_revision::Entity::find()
+                .filter(entity::template_revision::Column::TemplateId.eq(id))
+                .order_by_desc(entity::template_revision::Column::Revision)
+                .apply_params(&params)
+                .unwrap()
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|model| TemplateRevision::new(model, published))
+                .collect(),
+            total_count: entity::template_revision::Entity::find()
+                .filter(entity::template_revision::Column::TemplateId.eq(id))
+                .apply_filter(&params)
+                .unwrap()
+                .count(&self.db)
+                .await?,
+        })
+    }
+
+    async fn get_template_revision(
+        &self,
+        id: Uuid,
+        revision: i32,
+    ) -> Result<TemplateRevision, TemplaterError> {
+        let published = self.get_template_model(&self.db, id).await?.revision;
+
+        let model = entity::template_revision::Entity::find()
+            .filter(entity::template_revision::Column::TemplateId.eq(id))
+            .filter(entity::template_revision::Column::Revision.eq(revision))
+            .one(&self.db)
+            .await?
+            .ok_or(TemplaterError::RevisionNotFound)?;
+        Ok(TemplateRevision::new(model, published))
+    }
+
+    async fn publish_template_revision(
+        &self,
+        id: Uuid,
+        revision: i32,
+    ) -> Result<TemplateItem, TemplaterError> {
+        let txn = self.db.begin().await?;
+        self.get_template_model(&txn, id).await?;
+
+        let model = entity::template_revision::Entity::find()
+            .filter(entity::template_revision::Column::TemplateId.eq(id))
+            .filter(entity::template_revision::Column::Revision.eq(revision))
+            .one(&txn)
+            .await?
+            .ok_or(TemplaterError::RevisionNotFound)?;
+
+        let template = entity::template::ActiveModel {
+            id: Set(id),
+            template: Set(model.template.clone()),
+            revision: Set(revision),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        entity::template_revision::ActiveModel {
+            id: Set(model.id),
+            published_at: Set(Some(Utc::now().fixed_offset())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        let mut items = self.with_drafts(&txn, vec![template]).await?;
+        txn.commit().await?;
+
+        Ok(items.remove(0))
+    }
 }
 
 impl From<entity::template::Model> for PreRenderedTemplate {
@@ -123,3 +363,18 @@ impl From<entity::template::Model> for PreRenderedTemplate {
         PreRenderedTemplate(serde_json::from_value(value.template).unwrap_or(HashMap::new()))
     }
 }
+
+impl TemplateRevision {
+    fn new(value: entity::template_revision::Model, published: i32) -> Self {
+        Self {
+            template_id: value.template_id,
+            revision: value.revision,
+            template: PreRenderedTemplate(
+                serde_json::from_value(value.template).unwrap_or(HashMap::new()),
+            ),
+            created_at: value.created_at.to_utc(),
+            published_at: value.published_at.map(|t| t.to_utc()),
+            published: value.revision == published,
+        }
+    }
+}
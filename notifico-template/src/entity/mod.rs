@@ -3,3 +3,5 @@
 pub mod prelude;
 
 pub mod template;
+
+pub mod template_revision;
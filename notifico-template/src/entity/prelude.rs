@@ -1,3 +1,4 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.1
 
 pub use super::template::Entity as Template;
+pub use super::template_revision::Entity as TemplateRevision;
@@ -12,6 +12,7 @@ pub struct Model {
     pub channel: String,
     #[sea_orm(column_type = "JsonBinary")]
     pub template: Json,
+    pub revision: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
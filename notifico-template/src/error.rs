@@ -8,6 +8,8 @@ use thiserror::Error;
 pub enum TemplaterError {
     #[error("Template not found")]
     TemplateNotFound,
+    #[error("Template revision not found")]
+    RevisionNotFound,
     #[error("I/O error: {0}")]
     Io(io::Error),
     #[error("SeaORM error: {0}")]
@@ -8,14 +8,17 @@ use error::TemplaterError;
 use minijinja::Environment;
 use notifico_core::engine::{EnginePlugin, Message, PipelineContext, StepOutput};
 use notifico_core::error::EngineError;
-use notifico_core::step::SerializedStep;
+use notifico_core::metrics::TEMPLATE_RENDER_DURATION;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::templater::RenderedTemplate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use source::TemplateSource;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing::{debug, info};
 use uuid::Uuid;
 
@@ -54,18 +57,37 @@ impl Templater {
         template: PreRenderedTemplate,
         context: &Map<String, Value>,
     ) -> Result<RenderedTemplate, TemplaterError> {
-        let mut data = HashMap::new();
-        for (part_name, part_content) in template.0 {
-            // Render the template using the minijinja environment and the event context
-            let rendered_tpl = self.env.render_str(&part_content, context)?;
+        let started = Instant::now();
+        let result = render(&self.env, template, context);
+        metrics::histogram!(TEMPLATE_RENDER_DURATION).record(started.elapsed());
+        result
+    }
+}
 
-            // Insert the rendered template part into the data map
-            data.insert(part_name, rendered_tpl);
-        }
+/// Renders a template outside a pipeline, e.g. to preview a draft revision.
+pub fn preview_template(
+    template: PreRenderedTemplate,
+    context: &Map<String, Value>,
+) -> Result<RenderedTemplate, TemplaterError> {
+    render(&Environment::new(), template, context)
+}
 
-        // Return the rendered template data
-        Ok(RenderedTemplate(data))
+fn render(
+    env: &Environment,
+    template: PreRenderedTemplate,
+    context: &Map<String, Value>,
+) -> Result<RenderedTemplate, TemplaterError> {
+    let mut data = HashMap::new();
+    for (part_name, part_content) in template.0 {
+        // Render the template using the minijinja environment and the event context
+        let rendered_tpl = env.render_str(&part_content, context)?;
+
+        // Insert the rendered template part into the data map
+        data.insert(part_name, rendered_tpl);
     }
+
+    // Return the rendered template data
+    Ok(RenderedTemplate(data))
 }
 
 #[async_trait]
@@ -123,16 +145,20 @@ impl EnginePlugin for Templater {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         vec!["templates.load".into()]
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum TemplateSelector {
     ByName(String),
 }
 
 /// Represents a step in the notification pipeline.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "step")]
 enum Step {
     /// Loads templates for rendering.
@@ -1,6 +1,7 @@
 use crate::error::TemplaterError;
 use crate::{entity, PreRenderedTemplate, TemplateSelector};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -13,6 +14,18 @@ pub struct TemplateItem {
     pub channel: String,
     pub name: String,
     pub template: PreRenderedTemplate,
+    /// Published revision of the template.
+    #[serde(default)]
+    pub revision: i32,
+    /// Latest revision, if it is a draft newer than the published one.
+    #[serde(default, skip_deserializing)]
+    pub draft: Option<TemplateDraft>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TemplateDraft {
+    pub revision: i32,
+    pub template: PreRenderedTemplate,
 }
 
 impl From<entity::template::Model> for TemplateItem {
@@ -23,10 +36,25 @@ impl From<entity::template::Model> for TemplateItem {
             template: PreRenderedTemplate::from(value.clone()),
             channel: value.channel,
             name: value.name,
+            revision: value.revision,
+            draft: None,
         }
     }
 }
 
+/// Snapshot of the template content. Revisions are drafts until they are published.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TemplateRevision {
+    pub template_id: Uuid,
+    pub revision: i32,
+    pub template: PreRenderedTemplate,
+    pub created_at: DateTime<Utc>,
+    /// Last time the revision was published, if ever.
+    pub published_at: Option<DateTime<Utc>>,
+    /// Whether the revision is the one currently served by `get_template`.
+    pub published: bool,
+}
+
 #[async_trait]
 pub trait TemplateSource: Send + Sync + 'static {
     async fn get_template(
@@ -46,7 +74,30 @@ pub trait TemplateSource: Send + Sync + 'static {
 
     async fn create_template(&self, item: TemplateItem) -> Result<TemplateItem, TemplaterError>;
 
+    /// Updates the template name and channel, and saves its content as a new draft revision.
+    /// The published content stays unchanged until the draft is published.
+    /// Returns the template with the new draft.
     async fn update_template(&self, item: TemplateItem) -> Result<TemplateItem, TemplaterError>;
 
     async fn delete_template(&self, id: Uuid) -> Result<(), TemplaterError>;
+
+    async fn list_template_revisions(
+        &self,
+        id: Uuid,
+        params: ListQueryParams,
+    ) -> Result<PaginatedResult<TemplateRevision>, TemplaterError>;
+
+    async fn get_template_revision(
+        &self,
+        id: Uuid,
+        revision: i32,
+    ) -> Result<TemplateRevision, TemplaterError>;
+
+    /// Makes the revision the published content of the template.
+    /// Publishing an earlier revision rolls the template back to it.
+    async fn publish_template_revision(
+        &self,
+        id: Uuid,
+        revision: i32,
+    ) -> Result<TemplateItem, TemplaterError>;
 }
@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ThrottleHit::Table)
+                    .if_not_exists()
+                    .col(pk_uuid(ThrottleHit::Id))
+                    .col(string(ThrottleHit::Key))
+                    .col(timestamp_with_time_zone(ThrottleHit::CreatedAt))
+                    .col(timestamp_with_time_zone(ThrottleHit::ExpiresAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_throttle_hit_key")
+                    .table(ThrottleHit::Table)
+                    .col(ThrottleHit::Key)
+                    .col(ThrottleHit::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_throttle_hit_expires_at")
+                    .table(ThrottleHit::Table)
+                    .col(ThrottleHit::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ThrottleHit::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ThrottleHit {
+    Table,
+    Id,
+    Key,
+    CreatedAt,
+    ExpiresAt,
+}
@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use chrono::{TimeDelta, Utc};
+use migration::{Migrator, MigratorTrait};
+use notifico_core::error::EngineError;
+use notifico_core::throttle::ThrottleStore;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    Set,
+};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[allow(unused_imports)]
+mod entity;
+
+/// Sliding window throttling backed by the database, shared by all workers.
+pub struct DbThrottleStore {
+    db: DatabaseConnection,
+}
+
+impl DbThrottleStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn setup(&self) -> anyhow::Result<()> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    /// Deletes hits that are outside of their window. Returns the number of deleted hits.
+    pub async fn cleanup(&self) -> Result<u64, EngineError> {
+        let result = entity::throttle_hit::Entity::delete_many()
+            .filter(entity::throttle_hit::Column::ExpiresAt.lte(Utc::now().fixed_offset()))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+#[async_trait]
+impl ThrottleStore for DbThrottleStore {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        limit: u32,
+        window: Duration,
+    ) -> Result<bool, EngineError> {
+        let now = Utc::now();
+        let window = TimeDelta::from_std(window).unwrap_or(TimeDelta::MAX);
+        let id = Uuid::now_v7();
+
+        // Register the hit first, then count the hits that precede it.
+        // This way concurrent workers never let more than `limit` hits through.
+        entity::throttle_hit::ActiveModel {
+            id: Set(id),
+            key: Set(key.to_string()),
+            created_at: Set(now.fixed_offset()),
+            expires_at: Set(now.checked_add_signed(window).unwrap_or(now).fixed_offset()),
+        }
+        .insert(&self.db)
+        .await?;
+
+        let window_start = now.checked_sub_signed(window).unwrap_or_default();
+        let hits = entity::throttle_hit::Entity::find()
+            .filter(entity::throttle_hit::Column::Key.eq(key))
+            .filter(entity::throttle_hit::Column::CreatedAt.gt(window_start.fixed_offset()))
+            .filter(entity::throttle_hit::Column::Id.lte(id))
+            .count(&self.db)
+            .await?;
+
+        if hits > limit as u64 {
+            entity::throttle_hit::Entity::delete_by_id(id)
+                .exec(&self.db)
+                .await?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::Database;
+
+    async fn store() -> DbThrottleStore {
+        let store = DbThrottleStore::new(Database::connect("sqlite::memory:").await.unwrap());
+        store.setup().await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn admits_up_to_limit() {
+        let store = store().await;
+        let window = Duration::from_secs(60);
+
+        assert!(store.try_acquire("a", 2, window).await.unwrap());
+        assert!(store.try_acquire("a", 2, window).await.unwrap());
+        assert!(!store.try_acquire("a", 2, window).await.unwrap());
+        // Keys are throttled separately
+        assert!(store.try_acquire("b", 2, window).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejected_hits_are_not_counted() {
+        let store = store().await;
+        let window = Duration::from_secs(60);
+
+        assert!(store.try_acquire("a", 1, window).await.unwrap());
+        assert!(!store.try_acquire("a", 1, window).await.unwrap());
+        assert!(!store.try_acquire("a", 1, window).await.unwrap());
+
+        let hits = entity::throttle_hit::Entity::find()
+            .count(&store.db)
+            .await
+            .unwrap();
+        assert_eq!(hits, 1);
+    }
+
+    #[tokio::test]
+    async fn hits_leave_window() {
+        let store = store().await;
+        let window = Duration::from_secs(1);
+
+        assert!(store.try_acquire("a", 1, window).await.unwrap());
+        assert!(!store.try_acquire("a", 1, window).await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(store.try_acquire("a", 1, window).await.unwrap());
+        assert_eq!(store.cleanup().await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,278 @@
+use axum::extract::{Path, Query};
+use axum::http::header::CONTENT_RANGE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use chrono::{DateTime, Utc};
+use notifico_archive::{ArchiveFilter, ArchivedEvent, DbEventArchive};
+use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_core::pipeline::runner::{DryRunResult, PipelineRunner};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+use tracing::error;
+use uuid::Uuid;
+
+/// Number of archived events fetched at once during a replay.
+const REPLAY_PAGE_SIZE: u64 = 100;
+/// Maximum number of events run by a dry-run, as all of their results are returned at once.
+const DRY_RUN_LIMIT: u64 = 20;
+/// Number of replay jobs kept for status requests.
+const MAX_JOBS: usize = 100;
+/// Number of failures kept per replay job.
+const MAX_JOB_FAILURES: usize = 100;
+
+pub async fn list(
+    Query(params): Query<ListQueryParams>,
+    Extension(archive): Extension<Arc<DbEventArchive>>,
+) -> (HeaderMap, Json<Vec<ArchivedEvent>>) {
+    let PaginatedResult { items, total_count } =
+        archive.list_archived_events(params).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+fn default_rate() -> NonZeroU32 {
+    NonZeroU32::new(10).unwrap()
+}
+
+#[derive(Deserialize)]
+pub struct ReplayRequest {
+    /// `project_id`, `from` and `to` are required, unless it is a dry-run.
+    #[serde(flatten)]
+    filter: ArchiveFilter,
+    /// Run the pipelines without sending anything, see [`PipelineRunner::dry_run`].
+    #[serde(default)]
+    dry_run: bool,
+    /// Maximum number of events replayed per second.
+    #[serde(default = "default_rate")]
+    rate: NonZeroU32,
+}
+
+#[derive(Serialize, Default)]
+pub struct DryRunResponse {
+    replayed: Vec<Uuid>,
+    failed: Vec<ReplayFailure>,
+    /// Final state of the pipelines of every event.
+    results: Vec<DryRunEvent>,
+    /// More events match the filter than a dry-run runs.
+    truncated: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ReplayFailure {
+    id: Uuid,
+    message: String,
+}
+
+#[derive(Serialize)]
+pub struct DryRunEvent {
+    id: Uuid,
+    results: Vec<DryRunResult>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Progress of a replay running in the background.
+#[derive(Serialize, Clone)]
+pub struct ReplayJob {
+    id: Uuid,
+    status: ReplayStatus,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    replayed: u64,
+    failed: u64,
+    /// First failed events, up to 100.
+    failures: Vec<ReplayFailure>,
+    /// Error that has stopped the job.
+    error: Option<String>,
+}
+
+/// Replay jobs of this web instance. Jobs are kept in memory,
+/// so they are lost, and stop, when the instance is restarted.
+#[derive(Default)]
+pub struct ReplayJobs(Mutex<VecDeque<ReplayJob>>);
+
+impl ReplayJobs {
+    fn insert(&self, job: ReplayJob) {
+        let mut jobs = self.0.lock().unwrap();
+        if jobs.len() >= MAX_JOBS {
+            if let Some(idx) = jobs
+                .iter()
+                .position(|job| job.status != ReplayStatus::Running)
+            {
+                jobs.remove(idx);
+            }
+        }
+        jobs.push_back(job);
+    }
+
+    fn get(&self, id: Uuid) -> Option<ReplayJob> {
+        let jobs = self.0.lock().unwrap();
+        jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut ReplayJob)) {
+        let mut jobs = self.0.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            f(job);
+        }
+    }
+}
+
+/// Runs the archived events matching the filter through the current pipelines.
+///
+/// Pipelines are handed over to the workers by a background job, see
+/// [`PipelineRunner::schedule_event`]. Responds with the job, its progress is available
+/// at `/v1/archived_events/replay/:id`.
+/// Dry-runs are executed right away for the first 20 matching events and return the results instead.
+pub async fn replay(
+    Extension(archive): Extension<Arc<DbEventArchive>>,
+    Extension(runner): Extension<Arc<PipelineRunner>>,
+    Extension(jobs): Extension<Arc<ReplayJobs>>,
+    Json(request): Json<ReplayRequest>,
+) -> (StatusCode, Json<Value>) {
+    if request.dry_run {
+        let response = dry_run(&archive, &runner, &request.filter).await;
+        return (StatusCode::OK, Json(json!(response)));
+    }
+
+    let filter = &request.filter;
+    if filter.project_id.is_none() || filter.from.is_none() || filter.to.is_none() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({"message": "project_id, from and to are required"})),
+        );
+    }
+
+    let job = ReplayJob {
+        id: Uuid::now_v7(),
+        status: ReplayStatus::Running,
+        started_at: Utc::now(),
+        finished_at: None,
+        replayed: 0,
+        failed: 0,
+        failures: vec![],
+        error: None,
+    };
+    jobs.insert(job.clone());
+    tokio::spawn(run_replay(archive, runner, jobs, job.id, request));
+
+    (StatusCode::ACCEPTED, Json(json!(job)))
+}
+
+pub async fn get_replay(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(jobs): Extension<Arc<ReplayJobs>>,
+) -> (StatusCode, Json<Option<ReplayJob>>) {
+    match jobs.get(id) {
+        Some(job) => (StatusCode::OK, Json(Some(job))),
+        None => (StatusCode::NOT_FOUND, Json(None)),
+    }
+}
+
+async fn dry_run(
+    archive: &DbEventArchive,
+    runner: &PipelineRunner,
+    filter: &ArchiveFilter,
+) -> DryRunResponse {
+    let mut response = DryRunResponse::default();
+
+    // Fetch one more event to tell if there are more
+    let mut events = archive
+        .find_events(filter, None, DRY_RUN_LIMIT + 1)
+        .await
+        .unwrap();
+    if events.len() as u64 > DRY_RUN_LIMIT {
+        events.truncate(DRY_RUN_LIMIT as usize);
+        response.truncated = true;
+    }
+
+    for event in events {
+        let mut msg = event.request;
+        msg.dry_run = true;
+        match runner.dry_run(msg).await {
+            Ok(results) => {
+                response.replayed.push(event.id);
+                response.results.push(DryRunEvent {
+                    id: event.id,
+                    results,
+                });
+            }
+            Err(e) => response.failed.push(ReplayFailure {
+                id: event.id,
+                message: format!("{e:?}"),
+            }),
+        }
+    }
+    response
+}
+
+async fn run_replay(
+    archive: Arc<DbEventArchive>,
+    runner: Arc<PipelineRunner>,
+    jobs: Arc<ReplayJobs>,
+    job_id: Uuid,
+    request: ReplayRequest,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / request.rate.get());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut after = None;
+    loop {
+        let events = match archive
+            .find_events(&request.filter, after, REPLAY_PAGE_SIZE)
+            .await
+        {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Replay {job_id} has failed: {e:?}");
+                jobs.update(job_id, |job| {
+                    job.status = ReplayStatus::Failed;
+                    job.finished_at = Some(Utc::now());
+                    job.error = Some(format!("{e:?}"));
+                });
+                return;
+            }
+        };
+        let Some(last) = events.last() else {
+            break;
+        };
+        after = Some(last.id);
+
+        for event in events {
+            interval.tick().await;
+
+            let result = runner.schedule_event(event.request).await;
+            jobs.update(job_id, |job| match result {
+                Ok(()) => job.replayed += 1,
+                Err(e) => {
+                    job.failed += 1;
+                    if job.failures.len() < MAX_JOB_FAILURES {
+                        job.failures.push(ReplayFailure {
+                            id: event.id,
+                            message: format!("{e:?}"),
+                        });
+                    }
+                }
+            });
+        }
+    }
+
+    jobs.update(job_id, |job| {
+        job.status = ReplayStatus::Completed;
+        job.finished_at = Some(Utc::now());
+    });
+}
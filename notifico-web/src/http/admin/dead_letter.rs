@@ -0,0 +1,133 @@
+use axum::extract::{Path, Query};
+use axum::http::header::CONTENT_RANGE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_core::pipeline::dead_letter::{DeadLetter, ReplayMode};
+use notifico_core::pipeline::runner::PipelineRunner;
+use notifico_deadletter::DbDeadLetterQueue;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn list(
+    Query(params): Query<ListQueryParams>,
+    Extension(dead_letters): Extension<Arc<DbDeadLetterQueue>>,
+) -> (HeaderMap, Json<Vec<DeadLetter>>) {
+    let PaginatedResult { items, total_count } =
+        dead_letters.list_dead_letters(params).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+pub async fn get(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(dead_letters): Extension<Arc<DbDeadLetterQueue>>,
+) -> (StatusCode, Json<Option<DeadLetter>>) {
+    let result = dead_letters.get_dead_letter(id).await.unwrap();
+
+    let Some(result) = result else {
+        return (StatusCode::NOT_FOUND, Json(None));
+    };
+    (StatusCode::OK, Json(Some(result)))
+}
+
+pub async fn delete(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(dead_letters): Extension<Arc<DbDeadLetterQueue>>,
+) -> (StatusCode, Json<Value>) {
+    dead_letters.delete_dead_letter(id).await.unwrap();
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
+
+#[derive(Deserialize)]
+pub struct ReplayParams {
+    #[serde(default)]
+    mode: ReplayMode,
+}
+
+/// Hands the dead letter over to the workers, see [`PipelineRunner::replay`].
+pub async fn replay(
+    Path((id,)): Path<(Uuid,)>,
+    Query(params): Query<ReplayParams>,
+    Extension(dead_letters): Extension<Arc<DbDeadLetterQueue>>,
+    Extension(runner): Extension<Arc<PipelineRunner>>,
+) -> (StatusCode, Json<Value>) {
+    match replay_one(&dead_letters, &runner, id, params.mode).await {
+        Ok(()) => (StatusCode::ACCEPTED, Json(Value::Null)),
+        Err(ReplayError::NotFound) => (StatusCode::NOT_FOUND, Json(Value::Null)),
+        Err(ReplayError::Failed(error)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({"message": error})),
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BulkReplayRequest {
+    ids: Vec<Uuid>,
+    #[serde(default)]
+    mode: ReplayMode,
+}
+
+#[derive(Serialize, Default)]
+pub struct BulkReplayResponse {
+    replayed: Vec<Uuid>,
+    failed: Vec<BulkReplayFailure>,
+}
+
+#[derive(Serialize)]
+pub struct BulkReplayFailure {
+    id: Uuid,
+    message: String,
+}
+
+pub async fn replay_bulk(
+    Extension(dead_letters): Extension<Arc<DbDeadLetterQueue>>,
+    Extension(runner): Extension<Arc<PipelineRunner>>,
+    Json(request): Json<BulkReplayRequest>,
+) -> (StatusCode, Json<BulkReplayResponse>) {
+    let mut response = BulkReplayResponse::default();
+    for id in request.ids {
+        match replay_one(&dead_letters, &runner, id, request.mode).await {
+            Ok(()) => response.replayed.push(id),
+            Err(ReplayError::NotFound) => response.failed.push(BulkReplayFailure {
+                id,
+                message: "Dead letter not found".to_string(),
+            }),
+            Err(ReplayError::Failed(message)) => {
+                response.failed.push(BulkReplayFailure { id, message })
+            }
+        }
+    }
+
+    (StatusCode::ACCEPTED, Json(response))
+}
+
+enum ReplayError {
+    NotFound,
+    Failed(String),
+}
+
+async fn replay_one(
+    dead_letters: &DbDeadLetterQueue,
+    runner: &PipelineRunner,
+    id: Uuid,
+    mode: ReplayMode,
+) -> Result<(), ReplayError> {
+    let Some(letter) = dead_letters.get_dead_letter(id).await.unwrap() else {
+        return Err(ReplayError::NotFound);
+    };
+
+    runner
+        .replay(letter, mode)
+        .await
+        .map_err(|e| ReplayError::Failed(format!("{e:?}")))?;
+    dead_letters.mark_replayed(id).await.unwrap();
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use axum::extract::{Path, Query};
+use axum::http::header::CONTENT_RANGE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_recorder::{DbRecorder, Delivery};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn list(
+    Query(params): Query<ListQueryParams>,
+    Extension(recorder): Extension<Arc<DbRecorder>>,
+) -> (HeaderMap, Json<Vec<Delivery>>) {
+    let PaginatedResult { items, total_count } = recorder.list_deliveries(params).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+pub async fn get(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(recorder): Extension<Arc<DbRecorder>>,
+) -> (StatusCode, Json<Option<Delivery>>) {
+    let result = recorder.get_delivery_by_id(id).await.unwrap();
+
+    let Some(result) = result else {
+        return (StatusCode::NOT_FOUND, Json(None));
+    };
+    (StatusCode::OK, Json(Some(result)))
+}
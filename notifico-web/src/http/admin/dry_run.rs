@@ -0,0 +1,22 @@
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use notifico_core::error::EngineError;
+use notifico_core::pipeline::runner::{DryRunResult, PipelineRunner, ProcessEventRequest};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Transient errors, e.g. an unreachable recipient directory, are reported as 502.
+pub async fn dry_run(
+    Extension(runner): Extension<Arc<PipelineRunner>>,
+    Json(mut payload): Json<ProcessEventRequest>,
+) -> Result<Json<Vec<DryRunResult>>, (StatusCode, Json<Value>)> {
+    payload.dry_run = true;
+
+    runner.dry_run(payload).await.map(Json).map_err(|e| {
+        let (status, message) = match e {
+            EngineError::TransientError(message) => (StatusCode::BAD_GATEWAY, message),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}")),
+        };
+        (status, Json(json!({"message": message})))
+    })
+}
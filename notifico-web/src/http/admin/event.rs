@@ -3,9 +3,10 @@ use axum::http::header::CONTENT_RANGE;
 use axum::http::{HeaderMap, StatusCode};
 use axum::{Extension, Json};
 use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_core::pipeline::schema::{ContextValidationError, EventSchema};
 use notifico_core::pipeline::storage::PipelineStorage;
 use notifico_core::pipeline::Event;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use serde_json::{json, Value};
 use std::sync::Arc;
 use uuid::Uuid;
@@ -35,46 +36,89 @@ pub async fn get(
     (StatusCode::OK, Json(Some(result)))
 }
 
+/// Rejects schemas the ingest would not be able to validate events against.
+fn check_schema(
+    schema: Option<&Value>,
+) -> Result<(), (StatusCode, Json<Vec<ContextValidationError>>)> {
+    if let Some(schema) = schema {
+        EventSchema::new(schema).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, Json(vec![e])))?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct MatchingParams {
+    project_id: Uuid,
+    pattern: String,
+}
+
+/// Lists the registered events a pattern currently covers, see [`notifico_core::pipeline::pattern`].
+pub async fn list_matching(
+    Query(params): Query<MatchingParams>,
+    Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+) -> Json<Vec<Event>> {
+    let events = pipeline_storage
+        .get_events_matching(params.project_id, std::slice::from_ref(&params.pattern))
+        .await
+        .unwrap();
+
+    Json(events)
+}
+
 #[derive(Deserialize)]
 pub struct EventCreate {
     project_id: Uuid,
     name: String,
+    #[serde(default)]
+    schema: Option<Value>,
 }
 
 pub async fn create(
     Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
     Json(create): Json<EventCreate>,
-) -> (StatusCode, Json<Value>) {
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Vec<ContextValidationError>>)> {
+    check_schema(create.schema.as_ref())?;
+
     let result = pipeline_storage
-        .create_event(create.project_id, &create.name)
+        .create_event(create.project_id, &create.name, create.schema)
         .await
         .unwrap();
 
-    (
+    Ok((
         StatusCode::CREATED,
         Json(serde_json::to_value(result).unwrap()),
-    )
+    ))
 }
 
 #[derive(Deserialize)]
 pub struct EventUpdate {
     name: String,
+    /// Replaces the current schema, `null` removes it. The schema is kept if the field is missing.
+    #[serde(default, deserialize_with = "present")]
+    schema: Option<Option<Value>>,
+}
+
+/// Tells a field set to `null` from a missing one, which is `None` by `#[serde(default)]`.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<Value>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
 }
 
 pub async fn update(
     Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
     Path((id,)): Path<(Uuid,)>,
     Json(update): Json<EventUpdate>,
-) -> (StatusCode, Json<Value>) {
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Vec<ContextValidationError>>)> {
+    check_schema(update.schema.as_ref().and_then(Option::as_ref))?;
+
     let result = pipeline_storage
-        .update_event(id, &update.name)
+        .update_event(id, &update.name, update.schema)
         .await
         .unwrap();
 
-    (
+    Ok((
         StatusCode::CREATED,
         Json(serde_json::to_value(result).unwrap()),
-    )
+    ))
 }
 
 pub async fn delete(
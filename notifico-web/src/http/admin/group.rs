@@ -0,0 +1,136 @@
+use axum::extract::{Path, Query};
+use axum::http::header::CONTENT_RANGE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_recipient::{DbRecipientDirectory, RecipientGroupItem};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn list(
+    Query(params): Query<ListQueryParams>,
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+) -> (HeaderMap, Json<Vec<RecipientGroupItem>>) {
+    let PaginatedResult { items, total_count } = directory.list_groups(params).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+pub async fn get(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+) -> (StatusCode, Json<Option<RecipientGroupItem>>) {
+    let result = directory.get_group_by_id(id).await.unwrap();
+
+    let Some(result) = result else {
+        return (StatusCode::NOT_FOUND, Json(None));
+    };
+    (StatusCode::OK, Json(Some(result)))
+}
+
+pub async fn create(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Json(item): Json<RecipientGroupItem>,
+) -> (StatusCode, Json<RecipientGroupItem>) {
+    let result = directory.create_group(item).await.unwrap();
+
+    (StatusCode::CREATED, Json(result))
+}
+
+pub async fn update(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((id,)): Path<(Uuid,)>,
+    Json(mut update): Json<RecipientGroupItem>,
+) -> (StatusCode, Json<Value>) {
+    update.id = id;
+    directory.update_group(update).await.unwrap();
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::to_value(()).unwrap()),
+    )
+}
+
+pub async fn delete(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((id,)): Path<(Uuid,)>,
+) -> (StatusCode, Json<Value>) {
+    directory.delete_group(id).await.unwrap();
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
+
+pub async fn list_members(
+    Path((id,)): Path<(Uuid,)>,
+    Query(params): Query<ListQueryParams>,
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+) -> (HeaderMap, Json<Vec<String>>) {
+    let PaginatedResult { items, total_count } = directory.list_members(id, params).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+pub async fn add_members(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((id,)): Path<(Uuid,)>,
+    Json(recipient_ids): Json<Vec<String>>,
+) -> (StatusCode, Json<Value>) {
+    directory.add_members(id, recipient_ids).await.unwrap();
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
+
+pub async fn remove_members(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((id,)): Path<(Uuid,)>,
+    Json(recipient_ids): Json<Vec<String>>,
+) -> (StatusCode, Json<Value>) {
+    directory.remove_members(id, recipient_ids).await.unwrap();
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
+
+/// Adds recipients to a group addressed by name, creating the group on first use.
+/// Intended for services that maintain membership, e.g. ticket followers.
+pub async fn add_members_by_name(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((project_id, name)): Path<(Uuid, String)>,
+    Json(recipient_ids): Json<Vec<String>>,
+) -> (StatusCode, Json<Value>) {
+    let group = directory
+        .get_or_create_group(project_id, &name)
+        .await
+        .unwrap();
+    directory
+        .add_members(group.id, recipient_ids)
+        .await
+        .unwrap();
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
+
+pub async fn remove_members_by_name(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((project_id, name)): Path<(Uuid, String)>,
+    Json(recipient_ids): Json<Vec<String>>,
+) -> (StatusCode, Json<Value>) {
+    let group = directory
+        .get_group_by_name(project_id, &name)
+        .await
+        .unwrap();
+    if let Some(group) = group {
+        directory
+            .remove_members(group.id, recipient_ids)
+            .await
+            .unwrap();
+    }
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
@@ -1,10 +1,18 @@
 use crate::http::HttpExtensions;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Extension, Router};
+use std::sync::Arc;
 use tower_http::cors::CorsLayer;
+mod archive;
+mod dead_letter;
+mod delivery;
+mod dry_run;
 mod event;
+mod group;
 mod pipeline;
 mod project;
+mod recipient;
+mod step;
 pub mod subscription;
 mod template;
 
@@ -24,12 +32,70 @@ pub(crate) fn get_router(ext: HttpExtensions) -> Router {
                 .put(pipeline::update)
                 .delete(pipeline::delete),
         )
+        .route("/v1/pipelines/:id/revisions", get(pipeline::list_revisions))
+        .route(
+            "/v1/pipelines/:id/revisions/:revision",
+            get(pipeline::get_revision),
+        )
+        .route(
+            "/v1/pipelines/:id/revisions/:revision/rollback",
+            post(pipeline::rollback),
+        )
+        .route("/v1/pipelines/:id/diff", get(pipeline::diff))
+        .route("/v1/pipelines/:id/events", get(pipeline::list_events))
+        // Steps
+        .route("/v1/steps", get(step::list))
         // Events
         .route("/v1/events", get(event::list).post(event::create))
+        .route("/v1/events/matching", get(event::list_matching))
         .route(
             "/v1/events/:id",
             get(event::get).put(event::update).delete(event::delete),
         )
+        // Dry-run
+        .route("/v1/dry_run", post(dry_run::dry_run))
+        // Recipients
+        .route(
+            "/v1/recipients",
+            get(recipient::list).post(recipient::create),
+        )
+        .route(
+            "/v1/recipients/:id",
+            get(recipient::get)
+                .put(recipient::update)
+                .delete(recipient::delete),
+        )
+        // Recipient groups
+        .route("/v1/groups", get(group::list).post(group::create))
+        .route(
+            "/v1/groups/:id",
+            get(group::get).put(group::update).delete(group::delete),
+        )
+        .route(
+            "/v1/groups/:id/members",
+            get(group::list_members)
+                .post(group::add_members)
+                .delete(group::remove_members),
+        )
+        .route(
+            "/v1/projects/:id/groups/:name/members",
+            post(group::add_members_by_name).delete(group::remove_members_by_name),
+        )
+        // Dead letters
+        .route("/v1/dead_letters", get(dead_letter::list))
+        .route("/v1/dead_letters/replay", post(dead_letter::replay_bulk))
+        .route(
+            "/v1/dead_letters/:id",
+            get(dead_letter::get).delete(dead_letter::delete),
+        )
+        .route("/v1/dead_letters/:id/replay", post(dead_letter::replay))
+        // Event archive
+        .route("/v1/archived_events", get(archive::list))
+        .route("/v1/archived_events/replay", post(archive::replay))
+        .route("/v1/archived_events/replay/:id", get(archive::get_replay))
+        // Delivery log
+        .route("/v1/deliveries", get(delivery::list))
+        .route("/v1/deliveries/:id", get(delivery::get))
         // Projects
         .route(
             "/v1/projects",
@@ -51,10 +117,33 @@ pub(crate) fn get_router(ext: HttpExtensions) -> Router {
                 .put(template::update)
                 .delete(template::delete),
         )
+        .route(
+            "/v1/templates/:channel/:id/revisions",
+            get(template::list_revisions),
+        )
+        .route(
+            "/v1/templates/:channel/:id/revisions/:revision",
+            get(template::get_revision),
+        )
+        .route(
+            "/v1/templates/:channel/:id/revisions/:revision/preview",
+            post(template::preview_revision),
+        )
+        .route(
+            "/v1/templates/:channel/:id/revisions/:revision/publish",
+            post(template::publish_revision),
+        )
         // Layers
         .layer(Extension(ext.subman))
         .layer(Extension(ext.pipeline_storage))
         .layer(Extension(ext.projects_controller))
         .layer(Extension(ext.templates_controller))
+        .layer(Extension(ext.recorder))
+        .layer(Extension(ext.runner))
+        .layer(Extension(ext.dead_letters))
+        .layer(Extension(ext.archive))
+        .layer(Extension(Arc::new(archive::ReplayJobs::default())))
+        .layer(Extension(ext.recipients))
+        .layer(Extension(ext.engine))
         .layer(CorsLayer::permissive())
 }
@@ -2,11 +2,17 @@ use axum::extract::{Path, Query};
 use axum::http::header::CONTENT_RANGE;
 use axum::http::{HeaderMap, StatusCode};
 use axum::{Extension, Json};
+use notifico_core::engine::Engine;
+use notifico_core::error::EngineError;
 use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_core::pipeline::pattern;
+use notifico_core::pipeline::revision::{PipelineRevision, RevisionChange};
 use notifico_core::pipeline::storage::{PipelineResult, PipelineStorage};
-use notifico_core::pipeline::Pipeline;
+use notifico_core::pipeline::{Event, Pipeline};
+use notifico_core::step::{SerializedStep, StepValidationError};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashSet;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -15,51 +21,123 @@ pub struct PipelineItem {
     #[serde(default = "Uuid::nil")]
     pub id: Uuid,
     pub project_id: Uuid,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub priority: i32,
+    /// Current revision. Updates must be based on the current revision, or they are rejected with 409.
+    #[serde(default)]
+    pub revision: i32,
     pub event_ids: Vec<Uuid>,
+    /// Glob-style patterns of event names, e.g. `billing.*`.
+    #[serde(default)]
+    pub event_patterns: Vec<String>,
     pub steps: String,
     pub channel: String,
 }
 
+fn default_enabled() -> bool {
+    true
+}
+
 impl From<PipelineResult> for PipelineItem {
     fn from(value: PipelineResult) -> Self {
         Self {
             id: value.pipeline.id,
             project_id: value.pipeline.project_id,
+            name: value.pipeline.name,
+            description: value.pipeline.description,
+            enabled: value.pipeline.enabled,
+            priority: value.pipeline.priority,
+            revision: value.pipeline.revision,
             steps: serde_json::to_string(&value.pipeline.steps).unwrap(),
             channel: value.pipeline.channel,
 
             event_ids: value.event_ids,
+            event_patterns: value.event_patterns,
         }
     }
 }
 
+/// Parses and validates pipeline steps, so that broken pipelines are not stored.
+fn parse_steps(
+    engine: &Engine,
+    steps: &str,
+) -> Result<Vec<SerializedStep>, (StatusCode, Json<Vec<StepValidationError>>)> {
+    let steps: Vec<SerializedStep> = serde_json::from_str(steps).map_err(|e| {
+        let error = StepValidationError {
+            path: "".to_string(),
+            message: e.to_string(),
+        };
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(vec![error]))
+    })?;
+
+    let errors = engine.validate_steps(&steps);
+    if !errors.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(errors)));
+    }
+    Ok(steps)
+}
+
+/// Responds with 409 if the pipeline has been changed since the revision the change is based on.
+fn conflict<T>(error: EngineError) -> Result<T, (StatusCode, Json<Vec<StepValidationError>>)> {
+    match error {
+        EngineError::Conflict(message) => {
+            let error = StepValidationError {
+                path: "".to_string(),
+                message,
+            };
+            Err((StatusCode::CONFLICT, Json(vec![error])))
+        }
+        error => panic!("Failed to update pipeline: {error:?}"),
+    }
+}
+
+/// Author of the change, stored in the pipeline revision.
+const AUTHOR_HEADER: &str = "X-Author";
+
+fn author(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(AUTHOR_HEADER)
+        .and_then(|author| author.to_str().ok())
+        .map(str::to_string)
+}
+
 pub async fn create(
     Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+    Extension(engine): Extension<Arc<Engine>>,
+    headers: HeaderMap,
     Json(item): Json<PipelineItem>,
-) -> (StatusCode, Json<PipelineItem>) {
+) -> Result<(StatusCode, Json<PipelineItem>), (StatusCode, Json<Vec<StepValidationError>>)> {
+    let steps = parse_steps(&engine, &item.steps)?;
+
     let id = Uuid::now_v7();
     let pipeline = Pipeline {
         id,
         project_id: item.project_id,
+        name: item.name,
+        description: item.description,
+        enabled: item.enabled,
+        priority: item.priority,
+        revision: 0,
         channel: item.channel,
-        steps: serde_json::from_str(&item.steps).unwrap(),
+        steps,
     };
-    let _pipeline = pipeline_storage
-        .create_pipeline(pipeline.clone())
-        .await
-        .unwrap();
-
     let pipelineresult = PipelineResult {
         pipeline,
-        event_ids: item.event_ids.clone(),
+        event_ids: item.event_ids,
+        event_patterns: item.event_patterns,
     };
-
-    pipeline_storage
-        .assign_events_to_pipeline(id, item.event_ids.clone())
+    let pipelineresult = pipeline_storage
+        .create_pipeline(pipelineresult, author(&headers))
         .await
         .unwrap();
 
-    (StatusCode::CREATED, Json(pipelineresult.into()))
+    Ok((StatusCode::CREATED, Json(pipelineresult.into())))
 }
 
 pub async fn list(
@@ -95,25 +173,86 @@ pub async fn get(
 
 pub async fn update(
     Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+    Extension(engine): Extension<Arc<Engine>>,
     Path((id,)): Path<(Uuid,)>,
+    headers: HeaderMap,
     Json(update): Json<PipelineItem>,
-) -> (StatusCode, Json<Value>) {
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Vec<StepValidationError>>)> {
+    let steps = parse_steps(&engine, &update.steps)?;
+
     let pipeline = Pipeline {
         id,
         project_id: update.project_id,
+        name: update.name,
+        description: update.description,
+        enabled: update.enabled,
+        priority: update.priority,
+        revision: update.revision,
         channel: update.channel,
-        steps: serde_json::from_str(&update.steps).unwrap(),
+        steps,
+    };
+    let pipelineresult = PipelineResult {
+        pipeline,
+        event_ids: update.event_ids,
+        event_patterns: update.event_patterns,
     };
-    pipeline_storage.update_pipeline(pipeline).await.unwrap();
     pipeline_storage
-        .assign_events_to_pipeline(id, update.event_ids.clone())
+        .update_pipeline(pipelineresult, author(&headers))
         .await
-        .unwrap();
+        .or_else(conflict)?;
 
-    (
+    Ok((
         StatusCode::ACCEPTED,
         Json(serde_json::to_value(()).unwrap()),
-    )
+    ))
+}
+
+/// Registered event the pipeline is currently subscribed to.
+#[derive(Serialize)]
+pub struct PipelineEvent {
+    #[serde(flatten)]
+    event: Event,
+    /// Pattern covering the event, `null` if the event is linked to the pipeline directly.
+    pattern: Option<String>,
+}
+
+/// Resolves the linked events and event patterns of the pipeline to concrete events.
+pub async fn list_events(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+) -> (StatusCode, Json<Vec<PipelineEvent>>) {
+    let Some(result) = pipeline_storage.get_pipeline_by_id(id).await.unwrap() else {
+        return (StatusCode::NOT_FOUND, Json(vec![]));
+    };
+
+    let mut events = vec![];
+    let mut seen = HashSet::new();
+    for event_id in result.event_ids {
+        if let Some(event) = pipeline_storage.get_event_by_id(event_id).await.unwrap() {
+            seen.insert(event.id);
+            events.push(PipelineEvent {
+                event,
+                pattern: None,
+            });
+        }
+    }
+    let matched = pipeline_storage
+        .get_events_matching(result.pipeline.project_id, &result.event_patterns)
+        .await
+        .unwrap();
+    for event in matched {
+        if seen.contains(&event.id) {
+            continue;
+        }
+        let pattern = result
+            .event_patterns
+            .iter()
+            .find(|pattern| pattern::matches(pattern, &event.name))
+            .cloned();
+        events.push(PipelineEvent { event, pattern });
+    }
+
+    (StatusCode::OK, Json(events))
 }
 
 pub async fn delete(
@@ -124,3 +263,96 @@ pub async fn delete(
 
     (StatusCode::NO_CONTENT, Json(Value::Null))
 }
+
+pub async fn list_revisions(
+    Path((id,)): Path<(Uuid,)>,
+    Query(params): Query<ListQueryParams>,
+    Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+) -> (HeaderMap, Json<Vec<PipelineRevision>>) {
+    let PaginatedResult { items, total_count } = pipeline_storage
+        .list_pipeline_revisions(id, params)
+        .await
+        .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+pub async fn get_revision(
+    Path((id, revision)): Path<(Uuid, i32)>,
+    Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+) -> (StatusCode, Json<Option<PipelineRevision>>) {
+    let result = pipeline_storage
+        .get_pipeline_revision(id, revision)
+        .await
+        .unwrap();
+
+    let Some(result) = result else {
+        return (StatusCode::NOT_FOUND, Json(None));
+    };
+    (StatusCode::OK, Json(Some(result)))
+}
+
+#[derive(Deserialize)]
+pub struct DiffParams {
+    from: i32,
+    to: i32,
+}
+
+pub async fn diff(
+    Path((id,)): Path<(Uuid,)>,
+    Query(params): Query<DiffParams>,
+    Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+) -> (StatusCode, Json<Vec<RevisionChange>>) {
+    let from = pipeline_storage
+        .get_pipeline_revision(id, params.from)
+        .await
+        .unwrap();
+    let to = pipeline_storage
+        .get_pipeline_revision(id, params.to)
+        .await
+        .unwrap();
+
+    let (Some(from), Some(to)) = (from, to) else {
+        return (StatusCode::NOT_FOUND, Json(vec![]));
+    };
+    (StatusCode::OK, Json(from.diff(&to)))
+}
+
+/// Restores the pipeline from a revision. The result is stored as a new revision,
+/// the events of the pipeline are kept as they are.
+/// Steps the engine no longer accepts are rejected with 422, as on updates.
+pub async fn rollback(
+    Path((id, revision)): Path<(Uuid, i32)>,
+    Extension(pipeline_storage): Extension<Arc<dyn PipelineStorage>>,
+    Extension(engine): Extension<Arc<Engine>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<Option<PipelineItem>>), (StatusCode, Json<Vec<StepValidationError>>)>
+{
+    let revision = pipeline_storage
+        .get_pipeline_revision(id, revision)
+        .await
+        .unwrap();
+    let current = pipeline_storage.get_pipeline_by_id(id).await.unwrap();
+    let (Some(revision), Some(mut current)) = (revision, current) else {
+        return Ok((StatusCode::NOT_FOUND, Json(None)));
+    };
+
+    let errors = engine.validate_steps(&revision.pipeline.steps);
+    if !errors.is_empty() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(errors)));
+    }
+
+    current.pipeline = Pipeline {
+        revision: current.pipeline.revision,
+        ..revision.pipeline
+    };
+    let result = pipeline_storage
+        .update_pipeline(current, author(&headers))
+        .await
+        .or_else(conflict)?;
+
+    Ok((StatusCode::OK, Json(Some(result.into()))))
+}
@@ -0,0 +1,83 @@
+use axum::extract::{Path, Query};
+use axum::http::header::CONTENT_RANGE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::{Extension, Json};
+use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_core::recipient::parse_timezone;
+use notifico_recipient::{DbRecipientDirectory, RecipientItem};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn list(
+    Query(params): Query<ListQueryParams>,
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+) -> (HeaderMap, Json<Vec<RecipientItem>>) {
+    let PaginatedResult { items, total_count } = directory.list_recipients(params).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_RANGE, total_count.into());
+
+    (headers, Json(items))
+}
+
+pub async fn get(
+    Path((id,)): Path<(Uuid,)>,
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+) -> (StatusCode, Json<Option<RecipientItem>>) {
+    let result = directory.get_recipient_by_id(id).await.unwrap();
+
+    let Some(result) = result else {
+        return (StatusCode::NOT_FOUND, Json(None));
+    };
+    (StatusCode::OK, Json(Some(result)))
+}
+
+/// Rejects time zones the quiet hours could not be applied in.
+fn check_timezone(item: &RecipientItem) -> Result<(), (StatusCode, Json<Value>)> {
+    if let Some(timezone) = &item.timezone {
+        parse_timezone(timezone).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({"message": e})),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+pub async fn create(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Json(item): Json<RecipientItem>,
+) -> Result<(StatusCode, Json<RecipientItem>), (StatusCode, Json<Value>)> {
+    check_timezone(&item)?;
+    let result = directory.create_recipient(item).await.unwrap();
+
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+pub async fn update(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((id,)): Path<(Uuid,)>,
+    Json(mut update): Json<RecipientItem>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(error) = check_timezone(&update) {
+        return error;
+    }
+    update.id = id;
+    directory.update_recipient(update).await.unwrap();
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::to_value(()).unwrap()),
+    )
+}
+
+pub async fn delete(
+    Extension(directory): Extension<Arc<DbRecipientDirectory>>,
+    Path((id,)): Path<(Uuid,)>,
+) -> (StatusCode, Json<Value>) {
+    directory.delete_recipient(id).await.unwrap();
+
+    (StatusCode::NO_CONTENT, Json(Value::Null))
+}
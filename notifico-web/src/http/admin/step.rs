@@ -0,0 +1,12 @@
+use axum::{Extension, Json};
+use notifico_core::engine::Engine;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub async fn list(
+    Extension(engine): Extension<Arc<Engine>>,
+) -> Json<BTreeMap<Cow<'static, str>, Value>> {
+    Json(engine.step_schemas())
+}
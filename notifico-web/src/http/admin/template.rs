@@ -3,9 +3,11 @@ use axum::http::header::CONTENT_RANGE;
 use axum::http::{HeaderMap, StatusCode};
 use axum::{Extension, Json};
 use notifico_core::http::admin::{ListQueryParams, PaginatedResult};
+use notifico_core::templater::RenderedTemplate;
 use notifico_template::error::TemplaterError;
-use notifico_template::source::{TemplateItem, TemplateSource};
-use serde_json::Value;
+use notifico_template::preview_template;
+use notifico_template::source::{TemplateItem, TemplateRevision, TemplateSource};
+use serde_json::{json, Map, Value};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -46,6 +48,8 @@ pub async fn create(
     )
 }
 
+/// Saves the template content as a draft revision. It is not served until published.
+/// Responds with the template, the new revision is in its `draft`.
 pub async fn update(
     Extension(controller): Extension<Arc<dyn TemplateSource>>,
     Json(update): Json<TemplateItem>,
@@ -66,3 +70,70 @@ pub async fn delete(
 
     (StatusCode::NO_CONTENT, Json(Value::Null))
 }
+
+pub async fn list_revisions(
+    Path((_channel, id)): Path<(String, Uuid)>,
+    Query(params): Query<ListQueryParams>,
+    Extension(controller): Extension<Arc<dyn TemplateSource>>,
+) -> (StatusCode, HeaderMap, Json<Vec<TemplateRevision>>) {
+    let mut headers = HeaderMap::new();
+    match controller.list_template_revisions(id, params).await {
+        Ok(PaginatedResult { items, total_count }) => {
+            headers.insert(CONTENT_RANGE, total_count.into());
+            (StatusCode::OK, headers, Json(items))
+        }
+        Err(TemplaterError::TemplateNotFound) => (StatusCode::NOT_FOUND, headers, Json(vec![])),
+        Err(e) => panic!("{:?}", e),
+    }
+}
+
+pub async fn get_revision(
+    Path((_channel, id, revision)): Path<(String, Uuid, i32)>,
+    Extension(controller): Extension<Arc<dyn TemplateSource>>,
+) -> (StatusCode, Json<Option<TemplateRevision>>) {
+    match controller.get_template_revision(id, revision).await {
+        Ok(revision) => (StatusCode::OK, Json(Some(revision))),
+        Err(TemplaterError::TemplateNotFound | TemplaterError::RevisionNotFound) => {
+            (StatusCode::NOT_FOUND, Json(None))
+        }
+        Err(e) => panic!("{:?}", e),
+    }
+}
+
+/// Renders the revision with the event context from the request body.
+pub async fn preview_revision(
+    Path((_channel, id, revision)): Path<(String, Uuid, i32)>,
+    Extension(controller): Extension<Arc<dyn TemplateSource>>,
+    Json(context): Json<Map<String, Value>>,
+) -> Result<Json<RenderedTemplate>, (StatusCode, Json<Value>)> {
+    let revision = match controller.get_template_revision(id, revision).await {
+        Ok(revision) => revision,
+        Err(TemplaterError::TemplateNotFound | TemplaterError::RevisionNotFound) => {
+            return Err((StatusCode::NOT_FOUND, Json(Value::Null)))
+        }
+        Err(e) => panic!("{:?}", e),
+    };
+
+    preview_template(revision.template, &context)
+        .map(Json)
+        .map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({"message": e.to_string()})),
+            )
+        })
+}
+
+/// Publishes the revision. Publishing an earlier revision rolls the template back to it.
+pub async fn publish_revision(
+    Path((_channel, id, revision)): Path<(String, Uuid, i32)>,
+    Extension(controller): Extension<Arc<dyn TemplateSource>>,
+) -> (StatusCode, Json<Option<TemplateItem>>) {
+    match controller.publish_template_revision(id, revision).await {
+        Ok(template) => (StatusCode::OK, Json(Some(template))),
+        Err(TemplaterError::TemplateNotFound | TemplaterError::RevisionNotFound) => {
+            (StatusCode::NOT_FOUND, Json(None))
+        }
+        Err(e) => panic!("{:?}", e),
+    }
+}
@@ -4,8 +4,14 @@ use axum::http::header::CONTENT_TYPE;
 use axum::http::{StatusCode, Uri};
 use axum::response::{Html, IntoResponse, Response};
 use axum::Router;
+use notifico_archive::DbEventArchive;
+use notifico_core::engine::Engine;
+use notifico_core::pipeline::runner::PipelineRunner;
 use notifico_core::pipeline::storage::PipelineStorage;
+use notifico_deadletter::DbDeadLetterQueue;
 use notifico_project::ProjectController;
+use notifico_recipient::DbRecipientDirectory;
+use notifico_recorder::DbRecorder;
 use notifico_subscription::SubscriptionManager;
 use notifico_template::source::TemplateSource;
 use rust_embed::Embed;
@@ -19,6 +25,12 @@ pub(crate) struct HttpExtensions {
     pub pipeline_storage: Arc<dyn PipelineStorage>,
     pub projects_controller: Arc<ProjectController>,
     pub templates_controller: Arc<dyn TemplateSource>,
+    pub recorder: Arc<DbRecorder>,
+    pub runner: Arc<PipelineRunner>,
+    pub engine: Arc<Engine>,
+    pub recipients: Arc<DbRecipientDirectory>,
+    pub dead_letters: Arc<DbDeadLetterQueue>,
+    pub archive: Arc<DbEventArchive>,
 }
 
 #[derive(Embed)]
@@ -2,11 +2,23 @@ mod http;
 
 use crate::http::HttpExtensions;
 use clap::Parser;
+use notifico_archive::DbEventArchive;
+use notifico_core::config::credentials::MemoryCredentialStorage;
 use notifico_core::db::create_sqlite_if_not_exists;
+use notifico_core::pipeline::retry::RetryPolicy;
+use notifico_core::pipeline::runner::PipelineRunner;
 use notifico_dbpipeline::DbPipelineStorage;
+use notifico_deadletter::DbDeadLetterQueue;
+use notifico_digest::DbDigestStore;
+use notifico_engine::build_engine;
+use notifico_engine::directory::RecipientDirectoryArgs;
 use notifico_project::ProjectController;
+use notifico_recipient::DbRecipientDirectory;
+use notifico_recorder::DbRecorder;
+use notifico_scheduler::DbPipelineScheduler;
 use notifico_subscription::SubscriptionManager;
 use notifico_template::db::DbTemplateSource;
+use notifico_throttle::DbThrottleStore;
 use sea_orm::{ConnectOptions, Database};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -24,6 +36,9 @@ struct Args {
     bind: SocketAddr,
     #[clap(long, env = "NOTIFICO_USERAPI_URL")]
     userapi_url: Url,
+    /// Used by dry-runs and replays, must match the worker configuration
+    #[clap(flatten)]
+    recipient_directory: RecipientDirectoryArgs,
 }
 
 #[tokio::main]
@@ -67,11 +82,59 @@ async fn main() {
     let templates = Arc::new(DbTemplateSource::new(db_connection.clone())); // Implement your template source here
     templates.setup().await.unwrap();
 
+    let recorder = Arc::new(DbRecorder::new(db_connection.clone()));
+    recorder.setup().await.unwrap();
+
+    let recipients = Arc::new(DbRecipientDirectory::new(db_connection.clone()));
+    recipients.setup().await.unwrap();
+
+    let scheduler = Arc::new(DbPipelineScheduler::new(db_connection.clone()));
+    scheduler.setup().await.unwrap();
+
+    let throttle = Arc::new(DbThrottleStore::new(db_connection.clone()));
+    throttle.setup().await.unwrap();
+
+    let digests = Arc::new(DbDigestStore::new(db_connection.clone()));
+    digests.setup().await.unwrap();
+
+    let dead_letters = Arc::new(DbDeadLetterQueue::new(db_connection.clone()));
+    dead_letters.setup().await.unwrap();
+
+    let archive = Arc::new(DbEventArchive::new(db_connection.clone()));
+    archive.setup().await.unwrap();
+
+    // Engine for dry-runs. Transports stop before sending, so they need no credentials.
+    let credentials = Arc::new(MemoryCredentialStorage::default());
+
+    let engine = Arc::new(build_engine(
+        credentials,
+        recorder.clone(),
+        throttle,
+        digests,
+        templates.clone(),
+        subman.clone(),
+    ));
+    let runner = Arc::new(PipelineRunner::new(
+        pipeline_storage.clone(),
+        scheduler,
+        recorder.clone(),
+        Some(args.recipient_directory.build(recipients.clone())),
+        Some(recipients.clone()),
+        engine.as_ref().clone(),
+        RetryPolicy::default(),
+    ));
+
     let ext = HttpExtensions {
         projects_controller: projects,
         subman,
         pipeline_storage,
         templates_controller: templates,
+        recorder,
+        runner,
+        engine,
+        recipients,
+        dead_letters,
+        archive,
     };
 
     // Spawns HTTP servers and quits
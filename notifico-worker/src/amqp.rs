@@ -1,14 +1,26 @@
 use crate::Amqp;
 use fe2o3_amqp::acceptor::{ConnectionAcceptor, LinkAcceptor, LinkEndpoint, SessionAcceptor};
+use fe2o3_amqp::types::primitives::SimpleValue;
 use fe2o3_amqp::{Connection, Receiver, Session};
-use notifico_core::pipeline::runner::PipelineRunner;
+use notifico_archive::DbEventArchive;
+use notifico_core::metrics::{AMQP_RECONNECTS, EVENTS_DUPLICATE};
+use notifico_core::pipeline::dead_letter::{DeadLetter, DeadLetterPayload};
+use notifico_core::pipeline::runner::{PipelineRunner, ProcessEventRequest};
+use notifico_dedup::DbEventDeduplicator;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{error, info, info_span, Instrument};
 use url::Url;
 use uuid::Uuid;
 
-pub async fn start(runner: Arc<PipelineRunner>, config: Amqp, worker_addr: String) {
+pub async fn start(
+    runner: Arc<PipelineRunner>,
+    deduplicator: Option<Arc<DbEventDeduplicator>>,
+    archive: Option<Arc<DbEventArchive>>,
+    config: Amqp,
+    worker_addr: String,
+) {
     let worker_uuid = Uuid::new_v4();
 
     let container_id = format!("notifico-worker-{}", worker_uuid);
@@ -26,19 +38,29 @@ pub async fn start(runner: Arc<PipelineRunner>, config: Amqp, worker_addr: Strin
             while let Ok((stream, addr)) = tcp_listener.accept().await {
                 info!("Accepted p2p AMQP connection from: {}", addr);
                 let runner = runner.clone();
+                let deduplicator = deduplicator.clone();
+                let archive = archive.clone();
 
                 let mut connection = connection_acceptor.accept(stream).await.unwrap();
                 let _handle = tokio::spawn(async move {
                     let session_acceptor = SessionAcceptor::new();
                     while let Ok(mut session) = session_acceptor.accept(&mut connection).await {
                         let runner = runner.clone();
+                        let deduplicator = deduplicator.clone();
+                        let archive = archive.clone();
 
                         let _handle = tokio::spawn(async move {
                             let link_acceptor = LinkAcceptor::new();
                             match link_acceptor.accept(&mut session).await.unwrap() {
                                 LinkEndpoint::Sender(_) => {}
                                 LinkEndpoint::Receiver(receiver) => {
-                                    let res = process_link(receiver, runner.clone()).await;
+                                    let res = process_link(
+                                        receiver,
+                                        runner.clone(),
+                                        deduplicator,
+                                        archive,
+                                    )
+                                    .await;
                                     if let Err(e) = res {
                                         info!("Error processing AMQP connection: {}", e);
                                     }
@@ -50,12 +72,20 @@ pub async fn start(runner: Arc<PipelineRunner>, config: Amqp, worker_addr: Strin
             }
         }
         (Some(url), None) => loop {
-            let res =
-                connect_to_broker(url.clone(), &worker_addr, &container_id, runner.clone()).await;
+            let res = connect_to_broker(
+                url.clone(),
+                &worker_addr,
+                &container_id,
+                runner.clone(),
+                deduplicator.clone(),
+                archive.clone(),
+            )
+            .await;
             if let Err(e) = res {
                 info!("Error processing AMQP broker: {}", e);
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
             }
+            metrics::counter!(AMQP_RECONNECTS).increment(1);
         },
         _ => {
             panic!("Invalid AMQP configuration");
@@ -68,21 +98,109 @@ async fn connect_to_broker(
     address: &str,
     container_id: &str,
     runner: Arc<PipelineRunner>,
+    deduplicator: Option<Arc<DbEventDeduplicator>>,
+    archive: Option<Arc<DbEventArchive>>,
 ) -> anyhow::Result<()> {
     info!("Connecting to AMQP broker: {}", url);
     let mut connection = Connection::open(container_id, url.clone()).await?;
     info!("Connected to AMQP broker: {}", url);
     let mut session = Session::begin(&mut connection).await?;
     let receiver = Receiver::attach(&mut session, "rust-receiver-link-1", address).await?;
-    process_link(receiver, runner).await
+    process_link(receiver, runner, deduplicator, archive).await
 }
 
-async fn process_link(mut receiver: Receiver, runner: Arc<PipelineRunner>) -> anyhow::Result<()> {
+async fn process_link(
+    mut receiver: Receiver,
+    runner: Arc<PipelineRunner>,
+    deduplicator: Option<Arc<DbEventDeduplicator>>,
+    archive: Option<Arc<DbEventArchive>>,
+) -> anyhow::Result<()> {
     loop {
         let delivery = receiver.recv::<String>().await?;
 
         receiver.accept(&delivery).await?;
-        let eventrequest = serde_json::from_str(delivery.body())?;
-        runner.process_eventrequest(eventrequest).await;
+        let eventrequest: ProcessEventRequest = match serde_json::from_str(delivery.body()) {
+            Ok(eventrequest) => eventrequest,
+            Err(err) => {
+                error!("Failed to deserialize event: {err}");
+                let letter = DeadLetter::new(
+                    Uuid::nil(),
+                    None,
+                    err.to_string(),
+                    DeadLetterPayload::Message {
+                        body: delivery.body().clone(),
+                    },
+                );
+                runner.push_dead_letter(letter).await;
+                continue;
+            }
+        };
+
+        // Continue the trace started by the ingest
+        let span = info_span!(
+            "amqp.receive",
+            event_id = %eventrequest.id,
+            project = %eventrequest.project_id,
+            event = eventrequest.event,
+        );
+        let carrier: HashMap<String, String> = delivery
+            .message()
+            .application_properties
+            .iter()
+            .flat_map(|properties| properties.iter())
+            .filter_map(|(key, value)| match value {
+                SimpleValue::String(value) => Some((key.clone(), value.clone())),
+                _ => None,
+            })
+            .collect();
+        notifico_telemetry::extract_context(&span, &carrier);
+
+        async {
+            if let Some(deduplicator) = &deduplicator {
+                if is_duplicate(deduplicator, &eventrequest).await {
+                    return;
+                }
+            }
+            if let Some(archive) = &archive {
+                if !eventrequest.dry_run {
+                    if let Err(e) = archive.archive(&eventrequest).await {
+                        error!("Failed to archive event {}: {:?}", eventrequest.id, e);
+                    }
+                }
+            }
+            runner.process_eventrequest(eventrequest).await;
+        }
+        .instrument(span)
+        .await;
+    }
+}
+
+/// Checks whether the event has already been processed.
+/// If the check fails, the event is processed anyway.
+async fn is_duplicate(
+    deduplicator: &DbEventDeduplicator,
+    eventrequest: &ProcessEventRequest,
+) -> bool {
+    let key = eventrequest.deduplication_key();
+    match deduplicator
+        .mark_processed(eventrequest.project_id, &key)
+        .await
+    {
+        Ok(true) => false,
+        Ok(false) => {
+            info!(
+                "Skipping duplicate event {} (key: {key}, project: {})",
+                eventrequest.id, eventrequest.project_id
+            );
+            metrics::counter!(EVENTS_DUPLICATE).increment(1);
+            true
+        }
+        Err(e) => {
+            error!(
+                "Failed to check event {} for duplicates: {:?}",
+                eventrequest.id, e
+            );
+            false
+        }
     }
 }
@@ -0,0 +1,33 @@
+use chrono::{TimeDelta, Utc};
+use notifico_archive::DbEventArchive;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Checks that the retention, in seconds, can be subtracted from the current time.
+pub fn parse_retention(value: &str) -> Result<u64, String> {
+    let seconds: u64 = value.parse().map_err(|e| format!("{e}"))?;
+    i64::try_from(seconds)
+        .ok()
+        .and_then(TimeDelta::try_seconds)
+        .and_then(|retention| Utc::now().checked_sub_signed(retention))
+        .ok_or_else(|| "retention is too large".to_string())?;
+    Ok(seconds)
+}
+
+/// Periodically deletes archived events older than `retention`.
+pub async fn start(archive: Arc<DbEventArchive>, retention: TimeDelta) {
+    loop {
+        let Some(before) = Utc::now().checked_sub_signed(retention) else {
+            error!("Archive retention {retention} is out of range");
+            return;
+        };
+        match archive.cleanup(before).await {
+            Ok(deleted) => debug!("Deleted {deleted} archived events"),
+            Err(e) => error!("Failed to delete archived events: {:?}", e),
+        }
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
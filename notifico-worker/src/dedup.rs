@@ -0,0 +1,26 @@
+use notifico_dedup::{DbEventDeduplicator, MAX_TTL};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Checks that the TTL, in seconds, does not exceed [`MAX_TTL`].
+pub fn parse_ttl(value: &str) -> Result<u64, String> {
+    let seconds: u64 = value.parse().map_err(|e| format!("{e}"))?;
+    if seconds > MAX_TTL.as_secs() {
+        return Err(format!("TTL is longer than {}s", MAX_TTL.as_secs()));
+    }
+    Ok(seconds)
+}
+
+/// Periodically deletes expired deduplication records.
+pub async fn start(deduplicator: Arc<DbEventDeduplicator>) {
+    loop {
+        match deduplicator.cleanup().await {
+            Ok(deleted) => debug!("Deleted {deleted} expired deduplication records"),
+            Err(e) => error!("Failed to delete expired deduplication records: {:?}", e),
+        }
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
@@ -1,27 +1,35 @@
 mod amqp;
+mod archive;
+mod dedup;
+mod scheduler;
+mod throttle;
 
+use chrono::TimeDelta;
 use clap::Parser;
 use figment::{providers::Format, providers::Toml, Figment};
+use notifico_archive::DbEventArchive;
 use notifico_core::config::credentials::MemoryCredentialStorage;
 use notifico_core::db::create_sqlite_if_not_exists;
-use notifico_core::engine::Engine;
+use notifico_core::pipeline::retry::RetryPolicy;
 use notifico_core::pipeline::runner::PipelineRunner;
-use notifico_core::recorder::BaseRecorder;
 use notifico_dbpipeline::DbPipelineStorage;
-use notifico_slack::SlackPlugin;
-use notifico_smpp::SmppPlugin;
-use notifico_smtp::EmailPlugin;
+use notifico_deadletter::DbDeadLetterQueue;
+use notifico_dedup::DbEventDeduplicator;
+use notifico_digest::DbDigestStore;
+use notifico_engine::build_engine;
+use notifico_engine::directory::RecipientDirectoryArgs;
+use notifico_recipient::DbRecipientDirectory;
+use notifico_recorder::DbRecorder;
+use notifico_scheduler::DbPipelineScheduler;
 use notifico_subscription::SubscriptionManager;
-use notifico_telegram::TelegramPlugin;
 use notifico_template::db::DbTemplateSource;
-use notifico_template::Templater;
-use notifico_whatsapp::WaBusinessPlugin;
+use notifico_throttle::DbThrottleStore;
 use sea_orm::{ConnectOptions, Database};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use url::Url;
 
 #[derive(Parser, Debug)]
@@ -47,6 +55,43 @@ struct Args {
         default_value = "/var/lib/notifico/credentials.toml"
     )]
     credentials_path: PathBuf,
+
+    /// Default number of retries for steps failed with a transient error
+    #[clap(long, env = "NOTIFICO_RETRY_MAX_ATTEMPTS", default_value_t = 5)]
+    retry_max_attempts: u32,
+
+    /// How long processed event IDs are remembered to skip duplicates, in seconds. 0 disables deduplication
+    #[clap(
+        long,
+        env = "NOTIFICO_DEDUP_TTL",
+        default_value_t = 86400,
+        value_parser = dedup::parse_ttl
+    )]
+    dedup_ttl: u64,
+
+    /// How long received events are archived for replays, in seconds. 0 disables the archive
+    #[clap(
+        long,
+        env = "NOTIFICO_ARCHIVE_RETENTION",
+        default_value_t = 604800,
+        value_parser = archive::parse_retention
+    )]
+    archive_retention: u64,
+
+    #[clap(flatten)]
+    recipient_directory: RecipientDirectoryArgs,
+
+    /// OTLP gRPC endpoint to export traces to, e.g. `http://localhost:4317`
+    #[clap(long, env = "NOTIFICO_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<Url>,
+
+    /// Address of the Prometheus `/metrics` endpoint
+    #[clap(
+        long,
+        env = "NOTIFICO_WORKER_METRICS_BIND",
+        default_value = "[::]:8000"
+    )]
+    metrics_bind: SocketAddr,
 }
 
 #[derive(Debug, clap::Args)]
@@ -68,13 +113,12 @@ async fn main() {
 
     let args = Args::parse();
 
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
+    let _telemetry = notifico_telemetry::init("notifico-worker", args.otlp_endpoint.as_ref());
 
     info!("Config: {:#?}", args);
 
+    notifico_telemetry::serve_metrics(args.metrics_bind);
+
     create_sqlite_if_not_exists(&args.db_url);
 
     let mut db_conn_options = ConnectOptions::new(args.db_url.to_string());
@@ -93,47 +137,88 @@ async fn main() {
         Arc::new(MemoryCredentialStorage::from_config(credential_config).unwrap())
     };
     let pipelines = Arc::new(DbPipelineStorage::new(db_connection.clone()));
+    let recipients = Arc::new(DbRecipientDirectory::new(db_connection.clone()));
+    let dead_letters = Arc::new(DbDeadLetterQueue::new(db_connection.clone()));
+    let scheduler = Arc::new(
+        DbPipelineScheduler::new(db_connection.clone())
+            .with_dead_letter_queue(dead_letters.clone()),
+    );
+    let deduplicator = (args.dedup_ttl > 0).then(|| {
+        Arc::new(DbEventDeduplicator::new(
+            db_connection.clone(),
+            Duration::from_secs(args.dedup_ttl),
+        ))
+    });
+    let archive =
+        (args.archive_retention > 0).then(|| Arc::new(DbEventArchive::new(db_connection.clone())));
+    let recipient_directory = args.recipient_directory.build(recipients.clone());
 
     // Create Engine with plugins
-    let mut engine = Engine::new();
-
-    let recorder = Arc::new(BaseRecorder::new());
-
+    let recorder = Arc::new(DbRecorder::new(db_connection.clone()));
+    let throttle = Arc::new(DbThrottleStore::new(db_connection.clone()));
+    let digests = Arc::new(DbDigestStore::new(db_connection.clone()));
     let templater_source = Arc::new(DbTemplateSource::new(db_connection.clone()));
-    engine.add_plugin(Arc::new(Templater::new(templater_source)));
-
-    engine.add_plugin(Arc::new(TelegramPlugin::new(
-        credentials.clone(),
-        recorder.clone(),
-    )));
-    engine.add_plugin(Arc::new(EmailPlugin::new(
-        credentials.clone(),
-        recorder.clone(),
-    )));
-    engine.add_plugin(Arc::new(WaBusinessPlugin::new(
-        credentials.clone(),
-        recorder.clone(),
-    )));
-    engine.add_plugin(Arc::new(SmppPlugin::new(credentials.clone())));
-    engine.add_plugin(Arc::new(SlackPlugin::new(
-        credentials.clone(),
-        recorder.clone(),
-    )));
-
     let subman = Arc::new(SubscriptionManager::new(
         db_connection,
         args.secret_key.as_bytes().to_vec(),
         args.userapi_url,
     ));
-    engine.add_plugin(subman.clone());
+    let engine = build_engine(
+        credentials,
+        recorder.clone(),
+        throttle.clone(),
+        digests.clone(),
+        templater_source,
+        subman.clone(),
+    );
 
     // Setup stateful plugins
     subman.setup().await.unwrap();
+    recorder.setup().await.unwrap();
+    scheduler.setup().await.unwrap();
+    recipients.setup().await.unwrap();
+    throttle.setup().await.unwrap();
+    digests.setup().await.unwrap();
+    dead_letters.setup().await.unwrap();
+    tokio::spawn(throttle::start(throttle));
+    if let Some(deduplicator) = &deduplicator {
+        deduplicator.setup().await.unwrap();
+        tokio::spawn(dedup::start(deduplicator.clone()));
+    }
+    if let Some(archive) = &archive {
+        archive.setup().await.unwrap();
+        tokio::spawn(archive::start(
+            archive.clone(),
+            TimeDelta::seconds(args.archive_retention as i64),
+        ));
+    }
 
     // Create PipelineRunner, the core component of the Notifico system
-    let runner = Arc::new(PipelineRunner::new(pipelines.clone(), engine));
-
-    tokio::spawn(amqp::start(runner.clone(), args.amqp, args.amqp_addr));
+    let retry_policy = RetryPolicy {
+        max_attempts: args.retry_max_attempts,
+        ..Default::default()
+    };
+    let runner = Arc::new(
+        PipelineRunner::new(
+            pipelines.clone(),
+            scheduler.clone(),
+            recorder,
+            Some(recipient_directory),
+            Some(recipients.clone()),
+            engine,
+            retry_policy,
+        )
+        .with_dead_letter_queue(dead_letters),
+    );
+
+    tokio::spawn(amqp::start(
+        runner.clone(),
+        deduplicator,
+        archive,
+        args.amqp,
+        args.amqp_addr,
+    ));
+    tokio::spawn(scheduler::start(runner.clone(), scheduler));
 
     tokio::signal::ctrl_c().await.unwrap();
 }
@@ -0,0 +1,34 @@
+use notifico_core::pipeline::runner::PipelineRunner;
+use notifico_core::pipeline::scheduler::PipelineScheduler;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+
+const BATCH_SIZE: u64 = 100;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Periodically picks up suspended pipelines that are due and resumes them.
+pub async fn start(runner: Arc<PipelineRunner>, scheduler: Arc<dyn PipelineScheduler>) {
+    loop {
+        let tasks = match scheduler.take_due(BATCH_SIZE).await {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                error!("Failed to fetch scheduled pipelines: {:?}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+        };
+
+        if tasks.is_empty() {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        }
+
+        for task in tasks {
+            let runner = runner.clone();
+            tokio::spawn(async move {
+                runner.execute_pipeline(task.pipeline, task.context).await;
+            });
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use notifico_throttle::DbThrottleStore;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Periodically deletes throttle hits that are outside of their window.
+pub async fn start(throttle: Arc<DbThrottleStore>) {
+    loop {
+        match throttle.cleanup().await {
+            Ok(deleted) => debug!("Deleted {deleted} expired throttle hits"),
+            Err(e) => error!("Failed to delete expired throttle hits: {:?}", e),
+        }
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
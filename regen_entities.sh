@@ -31,3 +31,59 @@ sea-orm-cli migrate -d migration up
 sea-orm-cli generate entity -o src/entity --ignore-tables template_migrations
 rm "$TEMPDB"
 popd
+
+pushd notifico-recorder
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables recorder_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-scheduler
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables scheduler_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-recipient
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables recipient_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-dedup
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables dedup_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-throttle
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables throttle_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-digest
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables digest_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-deadletter
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables deadletter_migrations
+rm "$TEMPDB"
+popd
+
+pushd notifico-archive
+touch "$TEMPDB"
+sea-orm-cli migrate -d migration up
+sea-orm-cli generate entity -o src/entity --ignore-tables archive_migrations
+rm "$TEMPDB"
+popd
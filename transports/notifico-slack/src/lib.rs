@@ -8,11 +8,13 @@ use notifico_core::engine::{EnginePlugin, PipelineContext, StepOutput};
 use notifico_core::error::EngineError;
 use notifico_core::recipient::TypedContact;
 use notifico_core::recorder::Recorder;
-use notifico_core::step::SerializedStep;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::templater::RenderedTemplate;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::borrow::Cow;
 use std::sync::Arc;
+use tracing::Instrument;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SlackCredentials {
@@ -50,14 +52,17 @@ impl EnginePlugin for SlackPlugin {
 
         match step {
             Step::Send { credential } => {
+                let contact: SlackContact = context.get_contact()?;
+                if context.dry_run {
+                    return Ok(StepOutput::Continue);
+                }
+
                 let credential: SlackCredentials = self
                     .credentials
                     .get_typed_credential(context.project_id, &credential)
                     .await?;
 
-                let contact: SlackContact = context.get_contact()?;
-
-                for message in context.messages.iter().cloned() {
+                for message in context.pending_messages() {
                     let content: SlackMessage = message.content.try_into()?;
                     let slack_message = slackapi::SlackMessage::Text {
                         channel: contact.channel_id.clone(),
@@ -67,21 +72,19 @@ impl EnginePlugin for SlackPlugin {
                     let result = self
                         .client
                         .chat_post_message(&credential.token, slack_message)
+                        .instrument(context.transport_span("slack", message.id))
                         .await;
 
-                    match result {
-                        Ok(_) => self.recorder.record_message_sent(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                        ),
-                        Err(e) => self.recorder.record_message_failed(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                            &e.to_string(),
-                        ),
-                    }
+                    let result = result.map(|_| ()).map_err(|e| {
+                        if e.is_transient() {
+                            EngineError::TransientError(e.to_string())
+                        } else {
+                            EngineError::DeliveryFailed(e.to_string())
+                        }
+                    });
+                    context
+                        .record_delivery(self.recorder.as_ref(), message.id, result)
+                        .await?;
                 }
                 Ok(StepOutput::Continue)
             }
@@ -91,6 +94,10 @@ impl EnginePlugin for SlackPlugin {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| s.into()).collect()
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -27,6 +27,19 @@ pub enum SlackError {
     ApiError { error: String },
 }
 
+impl SlackError {
+    /// Whether the request may succeed if retried later.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SlackError::Request(_) => true,
+            SlackError::ApiError { error } => matches!(
+                error.as_str(),
+                "ratelimited" | "internal_error" | "request_timeout" | "service_unavailable"
+            ),
+        }
+    }
+}
+
 pub struct SlackApi {
     client: reqwest::Client,
 }
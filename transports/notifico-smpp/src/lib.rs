@@ -10,7 +10,8 @@ use notifico_core::credentials::CredentialStorage;
 use notifico_core::engine::{EnginePlugin, PipelineContext, StepOutput};
 use notifico_core::error::EngineError;
 use notifico_core::recipient::MobilePhoneContact;
-use notifico_core::step::SerializedStep;
+use notifico_core::recorder::Recorder;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::templater::RenderedTemplate;
 use rusmpp::commands::tlvs::tlv::message_submission_request::MessageSubmissionRequestTLVValue;
 use rusmpp::commands::types::{
@@ -34,15 +35,19 @@ use std::str::FromStr;
 use std::sync::Arc;
 use tokio::net::TcpStream;
 use tokio_util::codec::{FramedRead, FramedWrite};
-use tracing::debug;
+use tracing::{debug, warn, Instrument};
 
 pub struct SmppPlugin {
     credentials: Arc<dyn CredentialStorage>,
+    recorder: Arc<dyn Recorder>,
 }
 
 impl SmppPlugin {
-    pub fn new(credentials: Arc<dyn CredentialStorage>) -> Self {
-        Self { credentials }
+    pub fn new(credentials: Arc<dyn CredentialStorage>, recorder: Arc<dyn Recorder>) -> Self {
+        Self {
+            credentials,
+            recorder,
+        }
     }
 }
 
@@ -57,6 +62,11 @@ impl EnginePlugin for SmppPlugin {
 
         match step {
             Step::Send { credential } => {
+                let contact: MobilePhoneContact = context.get_contact()?;
+                if context.dry_run {
+                    return Ok(StepOutput::Continue);
+                }
+
                 let credential: SmppServerCredentials = self
                     .credentials
                     .get_typed_credential(context.project_id, &credential)
@@ -64,7 +74,7 @@ impl EnginePlugin for SmppPlugin {
 
                 let stream = TcpStream::connect((credential.host.clone(), credential.port))
                     .await
-                    .unwrap();
+                    .map_err(|e| EngineError::TransientError(e.to_string()))?;
 
                 let (reader, writer) = stream.into_split();
                 let mut framed_read = FramedRead::new(reader, CommandCodec {});
@@ -75,8 +85,8 @@ impl EnginePlugin for SmppPlugin {
                     CommandStatus::EsmeRok,
                     1,
                     Bind::builder()
-                        .system_id(COctetString::from_str(&credential.username).unwrap())
-                        .password(COctetString::from_str(&credential.password).unwrap())
+                        .system_id(octet_string(&credential.username)?)
+                        .password(octet_string(&credential.password)?)
                         .system_type(COctetString::empty())
                         .interface_version(InterfaceVersion::Smpp5_0)
                         .addr_ton(Ton::Unknown)
@@ -87,7 +97,10 @@ impl EnginePlugin for SmppPlugin {
                 );
 
                 // Send commands.
-                framed_write.send(&bind_transceiver_command).await.unwrap();
+                framed_write
+                    .send(&bind_transceiver_command)
+                    .await
+                    .map_err(|e| EngineError::TransientError(format!("{e:?}")))?;
 
                 // Wait for responses.
                 while let Some(Ok(command)) = framed_read.next().await {
@@ -101,69 +114,80 @@ impl EnginePlugin for SmppPlugin {
                     }
                 }
 
-                let contact: MobilePhoneContact = context.get_contact()?;
+                for message in context.pending_messages() {
+                    let submit_sm_command = match submit_sm(message.content, contact.msisdn()) {
+                        Ok(command) => command,
+                        Err(e) => {
+                            context
+                                .record_delivery(self.recorder.as_ref(), message.id, Err(e))
+                                .await?;
+                            continue;
+                        }
+                    };
+
+                    // Submit the message and wait for its delivery receipt
+                    let result = async {
+                        framed_write
+                            .send(&submit_sm_command)
+                            .await
+                            .map_err(|e| EngineError::TransientError(format!("{e:?}")))?;
 
-                for message in context.messages.iter().cloned() {
-                    let rendered: SmsContent = message.content.try_into().unwrap();
-
-                    let payload: Vec<u8> = rendered
-                        .body
-                        .encode_utf16()
-                        .flat_map(|c| c.to_be_bytes())
-                        .collect();
-
-                    let submit_sm_command = Command::new(
-                        CommandStatus::EsmeRok,
-                        2,
-                        SubmitSm::builder()
-                            .serivce_type(ServiceType::default())
-                            .source_addr_ton(Ton::Unknown)
-                            .source_addr_npi(Npi::Unknown)
-                            .source_addr(COctetString::from_str(&rendered.source_address).unwrap())
-                            .destination_addr(COctetString::from_str(contact.msisdn()).unwrap())
-                            .esm_class(EsmClass::default())
-                            .registered_delivery(RegisteredDelivery::request_all())
-                            .data_coding(DataCoding::Ucs2)
-                            .push_tlv(
-                                MessageSubmissionRequestTLVValue::MessagePayload(
-                                    AnyOctetString::new(&payload),
-                                )
-                                .into(),
-                            )
-                            .build()
-                            .into_submit_sm(),
-                    );
-
-                    framed_write.send(&submit_sm_command).await.unwrap();
-
-                    'outer: while let Some(Ok(command)) = framed_read.next().await {
-                        match command.pdu() {
-                            Some(Pdu::SubmitSmResp(_)) => {
-                                debug!("SubmitSmResp received.");
-
-                                if let CommandStatus::EsmeRok = command.command_status {
-                                    debug!("Successful submit.");
+                        let mut result = Err(EngineError::TransientError(
+                            "Connection closed before the message was submitted".to_string(),
+                        ));
+                        'outer: while let Some(Ok(command)) = framed_read.next().await {
+                            match command.pdu() {
+                                Some(Pdu::SubmitSmResp(_)) => {
+                                    debug!("SubmitSmResp received.");
+
+                                    match command.command_status {
+                                        CommandStatus::EsmeRok => {
+                                            debug!("Successful submit.");
+                                            result = Ok(());
+                                        }
+                                        status @ (CommandStatus::EsmeRthrottled
+                                        | CommandStatus::EsmeRmsgqful) => {
+                                            return Err(EngineError::TransientError(format!(
+                                                "{status:?}"
+                                            )));
+                                        }
+                                        status => {
+                                            return Err(EngineError::DeliveryFailed(format!(
+                                                "{status:?}"
+                                            )));
+                                        }
+                                    }
                                 }
-                            }
-                            Some(Pdu::DeliverSm(deliver_sm)) => {
-                                debug!("DeliverSm received.");
+                                Some(Pdu::DeliverSm(deliver_sm)) => {
+                                    debug!("DeliverSm received.");
 
-                                for tlv in deliver_sm.tlvs().iter() {
-                                    if let TLVTag::ReceiptedMessageId = tlv.tag() {
-                                        debug!("Delivery receipt received.");
+                                    for tlv in deliver_sm.tlvs().iter() {
+                                        if let TLVTag::ReceiptedMessageId = tlv.tag() {
+                                            debug!("Delivery receipt received.");
 
-                                        break 'outer;
+                                            break 'outer;
+                                        }
                                     }
                                 }
+                                _ => {}
                             }
-                            _ => {}
                         }
+                        result
                     }
+                    .instrument(context.transport_span("smpp", message.id))
+                    .await;
+                    context
+                        .record_delivery(self.recorder.as_ref(), message.id, result)
+                        .await?;
                 }
 
                 let unbind_command = Command::new(CommandStatus::EsmeRok, 3, Pdu::Unbind);
 
-                framed_write.send(&unbind_command).await.unwrap();
+                // Messages have been sent already, a failed unbind does not affect them
+                if let Err(e) = framed_write.send(&unbind_command).await {
+                    warn!("Failed to unbind: {e:?}");
+                    return Ok(StepOutput::Continue);
+                }
 
                 while let Some(Ok(command)) = framed_read.next().await {
                     if let CommandId::UnbindResp = command.command_id() {
@@ -184,6 +208,50 @@ impl EnginePlugin for SmppPlugin {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| Cow::from(s)).collect()
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
+}
+
+/// Converts a string to an SMPP string, which is limited in length and cannot contain NUL.
+fn octet_string<const MIN: usize, const MAX: usize>(
+    value: &str,
+) -> Result<COctetString<MIN, MAX>, EngineError> {
+    COctetString::from_str(value)
+        .map_err(|e| EngineError::DeliveryFailed(format!("Invalid SMPP string {value:?}: {e:?}")))
+}
+
+fn submit_sm(content: RenderedTemplate, msisdn: &str) -> Result<Command, EngineError> {
+    let rendered: SmsContent = content
+        .try_into()
+        .map_err(|_| EngineError::InvalidRenderedTemplateFormat("Invalid SMS content".into()))?;
+
+    let payload: Vec<u8> = rendered
+        .body
+        .encode_utf16()
+        .flat_map(|c| c.to_be_bytes())
+        .collect();
+
+    Ok(Command::new(
+        CommandStatus::EsmeRok,
+        2,
+        SubmitSm::builder()
+            .serivce_type(ServiceType::default())
+            .source_addr_ton(Ton::Unknown)
+            .source_addr_npi(Npi::Unknown)
+            .source_addr(octet_string(&rendered.source_address)?)
+            .destination_addr(octet_string(msisdn)?)
+            .esm_class(EsmClass::default())
+            .registered_delivery(RegisteredDelivery::request_all())
+            .data_coding(DataCoding::Ucs2)
+            .push_tlv(
+                MessageSubmissionRequestTLVValue::MessagePayload(AnyOctetString::new(&payload))
+                    .into(),
+            )
+            .build()
+            .into_submit_sm(),
+    ))
 }
 
 #[derive(Serialize, Deserialize, Clone)]
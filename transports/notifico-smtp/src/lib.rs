@@ -16,7 +16,7 @@ use lettre::{
 };
 use moka::future::Cache;
 use notifico_core::recorder::Recorder;
-use notifico_core::step::SerializedStep;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::{
     credentials::CredentialStorage,
     engine::{EnginePlugin, PipelineContext, StepOutput},
@@ -24,9 +24,11 @@ use notifico_core::{
     recipient::TypedContact,
 };
 use serde::Deserialize;
+use serde_json::Value;
 use std::borrow::Cow;
 use std::sync::Arc;
 use step::Step;
+use tracing::Instrument;
 
 #[derive(Debug, Deserialize)]
 pub struct EmailContact {
@@ -80,6 +82,9 @@ impl EnginePlugin for EmailPlugin {
         match step {
             Step::Send { credential } => {
                 let contact: EmailContact = context.get_contact()?;
+                if context.dry_run {
+                    return Ok(StepOutput::Continue);
+                }
 
                 let credential: SmtpServerCredentials = self
                     .credentials
@@ -91,7 +96,7 @@ impl EnginePlugin for EmailPlugin {
                 let plugin_context: PluginContext =
                     serde_json::from_value(context.plugin_contexts.clone().into()).unwrap();
 
-                for message in context.messages.iter().cloned() {
+                for message in context.pending_messages() {
                     let rendered: RenderedEmail = message.content.try_into()?;
 
                     let email_message = {
@@ -110,20 +115,20 @@ impl EnginePlugin for EmailPlugin {
                             .unwrap()
                     };
 
-                    let result = transport.send(email_message).await;
-                    match result {
-                        Ok(_) => self.recorder.record_message_sent(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                        ),
-                        Err(e) => self.recorder.record_message_failed(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                            &e.to_string(),
-                        ),
-                    }
+                    let result = transport
+                        .send(email_message)
+                        .instrument(context.transport_span("smtp", message.id))
+                        .await;
+                    let result = result.map(|_| ()).map_err(|e| {
+                        if e.is_permanent() {
+                            EngineError::DeliveryFailed(e.to_string())
+                        } else {
+                            EngineError::TransientError(e.to_string())
+                        }
+                    });
+                    context
+                        .record_delivery(self.recorder.as_ref(), message.id, result)
+                        .await?;
                 }
             }
         }
@@ -134,4 +139,8 @@ impl EnginePlugin for EmailPlugin {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| s.into()).collect()
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
 }
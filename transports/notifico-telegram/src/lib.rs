@@ -2,7 +2,7 @@ use crate::step::STEPS;
 use async_trait::async_trait;
 use contact::TelegramContact;
 use notifico_core::recorder::Recorder;
-use notifico_core::step::SerializedStep;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::{
     credentials::{CredentialStorage, TypedCredential},
     engine::PipelineContext,
@@ -13,10 +13,12 @@ use notifico_core::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::Cow;
+use std::future::IntoFuture;
 use std::sync::Arc;
 use step::Step;
 use teloxide::prelude::Requester;
-use teloxide::Bot;
+use teloxide::{Bot, RequestError};
+use tracing::Instrument;
 
 mod contact;
 mod step;
@@ -55,34 +57,42 @@ impl EnginePlugin for TelegramPlugin {
 
         match step {
             Step::Send { credential } => {
+                let contact: TelegramContact = context.get_contact()?;
+                if context.dry_run {
+                    return Ok(StepOutput::Continue);
+                }
+
                 let credential: TelegramBotCredentials = self
                     .credentials
                     .get_typed_credential(context.project_id, &credential)
                     .await?;
                 let bot = Bot::new(credential.token);
-                let contact: TelegramContact = context.get_contact()?;
 
-                for message in context.messages.iter().cloned() {
+                for message in context.pending_messages() {
                     let content: TelegramContent = message.content.try_into().unwrap();
 
                     // Send
                     let result = bot
                         .send_message(contact.clone().into_recipient(), content.body)
+                        .into_future()
+                        .instrument(context.transport_span("telegram", message.id))
                         .await;
 
-                    match result {
-                        Ok(_) => self.recorder.record_message_sent(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                        ),
-                        Err(e) => self.recorder.record_message_failed(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                            &e.to_string(),
-                        ),
-                    }
+                    let result = result.map(|_| ()).map_err(|e| {
+                        if matches!(
+                            e,
+                            RequestError::RetryAfter(_)
+                                | RequestError::Network(_)
+                                | RequestError::Io(_)
+                        ) {
+                            EngineError::TransientError(e.to_string())
+                        } else {
+                            EngineError::DeliveryFailed(e.to_string())
+                        }
+                    });
+                    context
+                        .record_delivery(self.recorder.as_ref(), message.id, result)
+                        .await?;
                 }
             }
         }
@@ -93,6 +103,10 @@ impl EnginePlugin for TelegramPlugin {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| s.into()).collect()
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -3,7 +3,7 @@ use crate::credentials::WhatsAppCredentials;
 use crate::step::{Step, STEPS};
 use async_trait::async_trait;
 use notifico_core::recorder::Recorder;
-use notifico_core::step::SerializedStep;
+use notifico_core::step::{step_schemas, SerializedStep};
 use notifico_core::{
     credentials::CredentialStorage,
     engine::PipelineContext,
@@ -12,10 +12,12 @@ use notifico_core::{
     recipient::MobilePhoneContact,
     templater::RenderedTemplate,
 };
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::Cow;
 use std::sync::Arc;
+use tracing::Instrument;
 
 mod cloudapi;
 mod credentials;
@@ -49,6 +51,9 @@ impl EnginePlugin for WaBusinessPlugin {
         match step {
             Step::Send { credential } => {
                 let contact: MobilePhoneContact = context.get_contact()?;
+                if context.dry_run {
+                    return Ok(StepOutput::Continue);
+                }
 
                 // Send
                 let credential: WhatsAppCredentials = self
@@ -61,7 +66,7 @@ impl EnginePlugin for WaBusinessPlugin {
                     credential.phone_id
                 );
 
-                for message in context.messages.iter().cloned() {
+                for message in context.pending_messages() {
                     let wa_message: WhatsAppContent = message.content.try_into().unwrap();
 
                     let wamessage = cloudapi::Message {
@@ -80,20 +85,19 @@ impl EnginePlugin for WaBusinessPlugin {
                         .header("Authorization", format!("Bearer {}", credential.token))
                         .json(&wamessage)
                         .send()
-                        .await;
-                    match result {
-                        Ok(_) => self.recorder.record_message_sent(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                        ),
-                        Err(e) => self.recorder.record_message_failed(
-                            context.event_id,
-                            context.notification_id,
-                            message.id,
-                            &e.to_string(),
-                        ),
-                    }
+                        .instrument(context.transport_span("whatsapp", message.id))
+                        .await
+                        .and_then(|resp| resp.error_for_status());
+                    let result = result.map(|_| ()).map_err(|e| {
+                        if is_transient(&e) {
+                            EngineError::TransientError(e.to_string())
+                        } else {
+                            EngineError::DeliveryFailed(e.to_string())
+                        }
+                    });
+                    context
+                        .record_delivery(self.recorder.as_ref(), message.id, result)
+                        .await?;
                 }
             }
         }
@@ -104,6 +108,18 @@ impl EnginePlugin for WaBusinessPlugin {
     fn steps(&self) -> Vec<Cow<'static, str>> {
         STEPS.iter().map(|&s| s.into()).collect()
     }
+
+    fn step_schemas(&self) -> Vec<(Cow<'static, str>, Value)> {
+        step_schemas::<Step>()
+    }
+}
+
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout()
+        || error.is_connect()
+        || error.status().is_some_and(|status| {
+            status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        })
 }
 
 #[derive(Serialize, Deserialize, Clone)]